@@ -0,0 +1,301 @@
+/// accounts.rs -> parsing & validating the accounts of an instruction, once & up front
+///
+/// Each struct takes the accounts in the order documented on its instruction, checking the signer & writable flags
+/// and the program ownership of each, so that an account-ordering mistake fails early with the role of the culprit.
+///
+/// Structs cover the instructions moving the tenant's funds: paying the rent, depositing, refunding & claiming the security deposit.
+/// The other handlers take their accounts inline with `next_account` (which also names the role of a missing account).
+
+use solana_program::{
+	account_info::{next_account_info, AccountInfo},
+	msg,
+	program_error::ProgramError,
+	pubkey::Pubkey,
+};
+
+use crate::error::TrustedPropertiesError;
+
+
+/// Get the next account, logging the role of the expected account if it is missing (eg: due to an account-ordering mistake)
+pub(crate) fn next_account<'a, 'b, I: Iterator<Item = &'a AccountInfo<'b>>>(accounts_iter: &mut I, role: &str) -> Result<I::Item, ProgramError> {
+	next_account_info(accounts_iter).map_err(|err| {
+		msg!("[TrustedProperties] ERROR: Missing account: expected the {}", role);
+		err
+	})
+}
+
+/// The account (eg: the rent agreement or escrow) must be owned by this program
+fn check_program_owned(account: &AccountInfo, program_id: &Pubkey, role: &str) -> Result<(), ProgramError> {
+	if account.owner != program_id {
+		msg!("[TrustedProperties] ERROR: The {} is not owned by this program", role);
+		return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+	}
+	Ok(())
+}
+
+fn check_signer(account: &AccountInfo, role: &str) -> Result<(), ProgramError> {
+	if !account.is_signer {
+		msg!("[TrustedProperties] ERROR: The {} must sign the instruction", role);
+		return Err(ProgramError::MissingRequiredSignature);
+	}
+	Ok(())
+}
+
+fn check_writable(account: &AccountInfo, role: &str) -> Result<(), ProgramError> {
+	if !account.is_writable {
+		msg!("[TrustedProperties] ERROR: The {} must be writable", role);
+		return Err(ProgramError::InvalidArgument);
+	}
+	Ok(())
+}
+
+/// A state account (eg: the rent agreement or escrow) the instruction stores data into must be writable.
+/// Checked up front, so that a read-only account fails with a clear error instead of a runtime failure on write.
+pub(crate) fn check_writable_state(account: &AccountInfo, role: &str) -> Result<(), ProgramError> {
+	if !account.is_writable {
+		msg!("[TrustedProperties] ERROR: The {} must be writable to store its state", role);
+		return Err(ProgramError::InvalidAccountData);
+	}
+	Ok(())
+}
+
+
+/// Accounts of `PayRent`
+pub struct PayRentAccounts<'a, 'b> {
+	pub agreement: &'a AccountInfo<'b>,
+	pub tenant: &'a AccountInfo<'b>,
+	pub owner: &'a AccountInfo<'b>,
+	pub system_program: &'a AccountInfo<'b>,
+	pub platform_wallet: Option<&'a AccountInfo<'b>>,
+}
+
+impl<'a, 'b> PayRentAccounts<'a, 'b> {
+	pub fn parse(accounts: &'a [AccountInfo<'b>], program_id: &Pubkey) -> Result<Self, ProgramError> {
+		let accounts_iter = &mut accounts.iter();
+
+		let agreement = next_account(accounts_iter, "rent agreement account")?;
+		check_program_owned(agreement, program_id, "rent agreement account")?;
+		check_writable_state(agreement, "rent agreement account")?;
+
+		let tenant = next_account(accounts_iter, "tenant signer")?;
+		check_signer(tenant, "tenant")?;
+		check_writable(tenant, "tenant account")?;
+
+		let owner = next_account(accounts_iter, "owner account")?;
+		check_writable(owner, "owner account")?;
+
+		let system_program = next_account(accounts_iter, "system program account")?;
+
+		let platform_wallet = accounts_iter.next();
+		if let Some(platform_wallet) = platform_wallet {
+			check_writable(platform_wallet, "platform wallet account")?;
+		}
+
+		Ok(Self { agreement, tenant, owner, system_program, platform_wallet })
+	}
+}
+
+
+/// Accounts of `DepositSecurity`
+pub struct DepositSecurityAccounts<'a, 'b> {
+	pub agreement: &'a AccountInfo<'b>,
+	pub tenant: &'a AccountInfo<'b>,
+	pub escrow: &'a AccountInfo<'b>,
+	pub system_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> DepositSecurityAccounts<'a, 'b> {
+	pub fn parse(accounts: &'a [AccountInfo<'b>], program_id: &Pubkey) -> Result<Self, ProgramError> {
+		let accounts_iter = &mut accounts.iter();
+
+		let agreement = next_account(accounts_iter, "rent agreement account")?;
+		check_program_owned(agreement, program_id, "rent agreement account")?;
+		check_writable_state(agreement, "rent agreement account")?;
+
+		let tenant = next_account(accounts_iter, "tenant signer")?;
+		check_signer(tenant, "tenant")?;
+		check_writable(tenant, "tenant account")?;
+
+		let escrow = next_account(accounts_iter, "escrow account")?;
+		check_program_owned(escrow, program_id, "security escrow account")?;
+		check_writable_state(escrow, "security escrow account")?;
+
+		let system_program = next_account(accounts_iter, "system program account")?;
+
+		Ok(Self { agreement, tenant, escrow, system_program })
+	}
+}
+
+
+/// Accounts of `RefundSecurityDeposit`
+pub struct RefundSecurityDepositAccounts<'a, 'b> {
+	pub agreement: &'a AccountInfo<'b>,
+	pub owner: &'a AccountInfo<'b>,
+	pub tenant: &'a AccountInfo<'b>,
+	pub escrow: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> RefundSecurityDepositAccounts<'a, 'b> {
+	pub fn parse(accounts: &'a [AccountInfo<'b>], program_id: &Pubkey) -> Result<Self, ProgramError> {
+		let accounts_iter = &mut accounts.iter();
+
+		let agreement = next_account(accounts_iter, "rent agreement account")?;
+		check_program_owned(agreement, program_id, "rent agreement account")?;
+
+		let owner = next_account(accounts_iter, "owner signer")?;
+		check_signer(owner, "owner")?;
+
+		let tenant = next_account(accounts_iter, "tenant account")?;
+		check_writable(tenant, "tenant account")?;
+
+		let escrow = next_account(accounts_iter, "escrow account")?;
+		check_program_owned(escrow, program_id, "security escrow account")?;
+		check_writable_state(escrow, "security escrow account")?;
+
+		Ok(Self { agreement, owner, tenant, escrow })
+	}
+}
+
+
+/// Accounts of `ClaimRefund`
+pub struct ClaimRefundAccounts<'a, 'b> {
+	pub agreement: &'a AccountInfo<'b>,
+	pub tenant: &'a AccountInfo<'b>,
+	pub escrow: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> ClaimRefundAccounts<'a, 'b> {
+	pub fn parse(accounts: &'a [AccountInfo<'b>], program_id: &Pubkey) -> Result<Self, ProgramError> {
+		let accounts_iter = &mut accounts.iter();
+
+		let agreement = next_account(accounts_iter, "rent agreement account")?;
+		check_program_owned(agreement, program_id, "rent agreement account")?;
+
+		let tenant = next_account(accounts_iter, "tenant signer")?;
+		check_signer(tenant, "tenant")?;
+		check_writable(tenant, "tenant account")?;
+
+		let escrow = next_account(accounts_iter, "escrow account")?;
+		check_program_owned(escrow, program_id, "security escrow account")?;
+		check_writable_state(escrow, "security escrow account")?;
+
+		Ok(Self { agreement, tenant, escrow })
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::state::test_utils::TestAccount;
+
+	/// An account as passed by the client
+	fn account(owner: Pubkey, is_signer: bool, is_writable: bool) -> TestAccount {
+		TestAccount { is_signer, is_writable, ..TestAccount::new(owner, 0, vec![]) }
+	}
+
+	/// Parse the accounts passed in the given `order` (indexes into `accounts`), returning the keys of the parsed accounts
+	fn parse_in_order(accounts: &mut [TestAccount], order: &[usize], program_id: &Pubkey, parse: fn(&[AccountInfo], &Pubkey) -> Result<Vec<Pubkey>, ProgramError>) -> Result<Vec<Pubkey>, ProgramError> {
+		let infos: Vec<AccountInfo> = accounts.iter_mut().map(|account| account.account_info()).collect();
+		let ordered: Vec<AccountInfo> = order.iter().map(|i| infos[*i].clone()).collect();
+		parse(&ordered, program_id)
+	}
+
+	fn keys_of(accounts: &[&AccountInfo]) -> Vec<Pubkey> {
+		accounts.iter().map(|account| *account.key).collect()
+	}
+
+	fn keys(accounts: &[TestAccount]) -> Vec<Pubkey> {
+		accounts.iter().map(|account| account.key).collect()
+	}
+
+	#[test]
+	fn test_pay_rent_accounts() {
+		let program_id = Pubkey::new_unique();
+		let mut accounts = [
+			account(program_id, false, true),
+			account(Pubkey::default(), true, true),
+			account(Pubkey::default(), false, true),
+			account(Pubkey::default(), false, false),
+			account(Pubkey::default(), false, true),
+		];
+		let parse = |accounts: &[AccountInfo], program_id: &Pubkey| {
+			PayRentAccounts::parse(accounts, program_id).map(|parsed| {
+				let mut parsed_accounts = vec![parsed.agreement, parsed.tenant, parsed.owner, parsed.system_program];
+				parsed_accounts.extend(parsed.platform_wallet);
+				keys_of(&parsed_accounts)
+			})
+		};
+
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 2, 3, 4], &program_id, parse), Ok(keys(&accounts)));
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 2, 3], &program_id, parse), Ok(keys(&accounts[..4])));
+
+		// Scrambled: tenant & owner, agreement & tenant, owner & system program swapped; the system program missing
+		assert_eq!(parse_in_order(&mut accounts, &[0, 2, 1, 3], &program_id, parse), Err(ProgramError::MissingRequiredSignature));
+		assert_eq!(parse_in_order(&mut accounts, &[1, 0, 2, 3], &program_id, parse), Err(TrustedPropertiesError::InvalidAccountOwner.into()));
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 3, 2], &program_id, parse), Err(ProgramError::InvalidArgument));
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 2], &program_id, parse), Err(ProgramError::NotEnoughAccountKeys));
+	}
+
+	#[test]
+	fn test_deposit_security_accounts() {
+		let program_id = Pubkey::new_unique();
+		let mut accounts = [
+			account(program_id, false, true),
+			account(Pubkey::default(), true, true),
+			account(program_id, false, true),
+			account(Pubkey::default(), false, false),
+		];
+		let parse = |accounts: &[AccountInfo], program_id: &Pubkey| {
+			DepositSecurityAccounts::parse(accounts, program_id).map(|parsed| keys_of(&[parsed.agreement, parsed.tenant, parsed.escrow, parsed.system_program]))
+		};
+
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 2, 3], &program_id, parse), Ok(keys(&accounts)));
+
+		// Scrambled: escrow & system program, agreement & tenant, tenant & escrow swapped
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 3, 2], &program_id, parse), Err(TrustedPropertiesError::InvalidAccountOwner.into()));
+		assert_eq!(parse_in_order(&mut accounts, &[1, 0, 2, 3], &program_id, parse), Err(TrustedPropertiesError::InvalidAccountOwner.into()));
+		assert_eq!(parse_in_order(&mut accounts, &[0, 2, 1, 3], &program_id, parse), Err(ProgramError::MissingRequiredSignature));
+	}
+
+	#[test]
+	fn test_refund_security_deposit_accounts() {
+		let program_id = Pubkey::new_unique();
+		let mut accounts = [
+			account(program_id, false, false),
+			account(Pubkey::default(), true, false),
+			account(Pubkey::default(), false, true),
+			account(program_id, false, true),
+		];
+		let parse = |accounts: &[AccountInfo], program_id: &Pubkey| {
+			RefundSecurityDepositAccounts::parse(accounts, program_id).map(|parsed| keys_of(&[parsed.agreement, parsed.owner, parsed.tenant, parsed.escrow]))
+		};
+
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 2, 3], &program_id, parse), Ok(keys(&accounts)));
+
+		// Scrambled: owner & tenant, tenant & escrow, agreement & owner swapped
+		assert_eq!(parse_in_order(&mut accounts, &[0, 2, 1, 3], &program_id, parse), Err(ProgramError::MissingRequiredSignature));
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 3, 2], &program_id, parse), Err(TrustedPropertiesError::InvalidAccountOwner.into()));
+		assert_eq!(parse_in_order(&mut accounts, &[1, 0, 2, 3], &program_id, parse), Err(TrustedPropertiesError::InvalidAccountOwner.into()));
+	}
+
+	#[test]
+	fn test_claim_refund_accounts() {
+		let program_id = Pubkey::new_unique();
+		let mut accounts = [
+			account(program_id, false, false),
+			account(Pubkey::default(), true, true),
+			account(program_id, false, true),
+		];
+		let parse = |accounts: &[AccountInfo], program_id: &Pubkey| {
+			ClaimRefundAccounts::parse(accounts, program_id).map(|parsed| keys_of(&[parsed.agreement, parsed.tenant, parsed.escrow]))
+		};
+
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1, 2], &program_id, parse), Ok(keys(&accounts)));
+
+		// Scrambled: tenant & escrow, agreement & tenant swapped; the escrow missing
+		assert_eq!(parse_in_order(&mut accounts, &[0, 2, 1], &program_id, parse), Err(ProgramError::MissingRequiredSignature));
+		assert_eq!(parse_in_order(&mut accounts, &[1, 0, 2], &program_id, parse), Err(TrustedPropertiesError::InvalidAccountOwner.into()));
+		assert_eq!(parse_in_order(&mut accounts, &[0, 1], &program_id, parse), Err(ProgramError::NotEnoughAccountKeys));
+	}
+}
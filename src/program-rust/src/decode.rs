@@ -0,0 +1,51 @@
+/// decode.rs -> decoding the program accounts, the supported entry point for off-chain tooling (eg: indexers)
+///
+/// Compiled for both BPF & the host, so that tools don't have to copy the state definitions.
+
+use borsh::BorshDeserialize;
+use solana_program::{msg, program_error::ProgramError};
+use std::convert::TryFrom;
+
+use crate::state::{AgreementStatus, RentAgreementAccount, SecurityEscrowAccount};
+
+
+/// Decode the data of a Rent Agreement account. Older layout versions are migrated to the current one.
+pub fn decode_rent_agreement(data: &[u8]) -> Result<RentAgreementAccount, ProgramError> {
+	if data.is_empty() {
+		msg!("[TrustedProperties] ERROR: Cannot decode the Rent Agreement account: no data");
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	RentAgreementAccount::try_from_slice_versioned(data).map_err(|err| {
+		msg!("[TrustedProperties] ERROR: Cannot decode the Rent Agreement account (layout version {}, {} bytes): {}", data[0], data.len(), err);
+		ProgramError::InvalidAccountData
+	})
+}
+
+/// Read only the status of a Rent Agreement account, without decoding the rest of the (growing) layout.
+/// Every layout version starts with the `schema_version` byte followed by the `status` byte, so only byte 1 is read.
+pub fn read_status(data: &[u8]) -> Result<AgreementStatus, ProgramError> {
+	match data.get(1) {
+		Some(status) => AgreementStatus::try_from(*status).map_err(|err| {
+			msg!("[TrustedProperties] ERROR: Unknown Rent Agreement status: {}", status);
+			err
+		}),
+		None => {
+			msg!("[TrustedProperties] ERROR: Cannot read the Rent Agreement status: {} bytes of data", data.len());
+			Err(ProgramError::InvalidAccountData)
+		}
+	}
+}
+
+/// Decode the data of a Security Deposit Escrow account
+pub fn decode_security_escrow(data: &[u8]) -> Result<SecurityEscrowAccount, ProgramError> {
+	if data.is_empty() {
+		msg!("[TrustedProperties] ERROR: Cannot decode the Security Escrow account: no data");
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	SecurityEscrowAccount::try_from_slice(data).map_err(|err| {
+		msg!("[TrustedProperties] ERROR: Cannot decode the Security Escrow account ({} bytes): {}", data.len(), err);
+		ProgramError::InvalidAccountData
+	})
+}
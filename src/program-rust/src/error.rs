@@ -4,35 +4,113 @@ use thiserror::Error;
 
 use solana_program::program_error::ProgramError;
 
-#[derive(Error, Debug, Copy, Clone)]
+/// Error codes are explicit (and must never change), so that the `ProgramError::Custom` code clients see
+/// does not depend on the order of the variants.
+#[derive(Error, Debug, Copy, Clone, PartialEq)]
 pub enum TrustedPropertiesError {
 	/// Invalid instruction
 	#[error("Invalid Instruction")]
-	InvalidInstruction,
+	InvalidInstruction = 1000,
 
 	/// Incorrect amount (deposit or rent payment) as per the agreement
 	#[error("Incorrect Payment Amount")]
-	IncorrectPaymentAmount,
+	IncorrectPaymentAmount = 1001,
 
 	/// Rent already paid in full
 	#[error("Full Rent Already Paid")]
-	RentAlreadyFullyPaid,
+	RentAlreadyFullyPaid = 1002,
 
 	/// Security amount already deposited
 	#[error("Security Amount Already Deposited")]
-	SecurityAlreadyDeposited,
+	SecurityAlreadyDeposited = 1003,
 
 	/// Rent agreement already terminated
 	#[error("Rent Agreement Already Terminated")]
-	RentAgreementTerminated,
+	RentAgreementTerminated = 1004,
 
 	/// Invalid agreement status
 	#[error("Invalid Agreement Status")]
-	InvalidAgreementStatus,
+	InvalidAgreementStatus = 1005,
 
 	/// Invalid instruction parameter
 	#[error("Invalid Instruction Parameter")]
-	InvalidInstructionParameter,
+	InvalidInstructionParameter = 1006,
+
+	/// Escrow account balance does not cover the tracked security deposit
+	#[error("Escrow Insolvent")]
+	EscrowInsolvent = 1007,
+
+	/// Security deposit refund still locked (refund delay after completion not elapsed)
+	#[error("Refund Locked")]
+	RefundLocked = 1008,
+
+	/// Initialization of new agreements is paused by the program authority
+	#[error("Program Paused")]
+	ProgramPaused = 1009,
+
+	/// Agreement state is internally inconsistent (eg: completed with payments remaining)
+	#[error("Corrupt State")]
+	CorruptState = 1010,
+
+	/// Agreement, escrow or config account is not owned by this program
+	#[error("Invalid Account Owner")]
+	InvalidAccountOwner = 1011,
+
+	/// A lamport computation (eg: escalated rent, total rent) does not fit in a `u64`
+	#[error("Arithmetic Overflow")]
+	ArithmeticOverflow = 1012,
+
+	/// Account does not match the owner stored in the agreement
+	#[error("Owner Mismatch")]
+	OwnerMismatch = 1013,
+
+	/// Account does not match the tenant stored in the agreement
+	#[error("Tenant Mismatch")]
+	TenantMismatch = 1014,
+
+	/// The Clock sysvar is not available on this runtime (required by time-dependent instructions)
+	#[error("Clock Unavailable")]
+	ClockUnavailable = 1015,
+
+	/// The rent payment is made earlier than the payment window (before the due date) of the agreement
+	#[error("Payment Too Early")]
+	PaymentTooEarly = 1016,
+
+	/// The agreement is finalized (completed or terminated): it only accepts refunds, renewal & reads
+	#[error("Agreement Finalized")]
+	AgreementFinalized = 1017,
+
+	/// The agreement is sealed as a permanent record: it can no longer be changed
+	#[error("Agreement Sealed")]
+	AgreementSealed = 1018,
+}
+
+impl TrustedPropertiesError {
+	/// The error for a `ProgramError::Custom` code, if it is one of this program's
+	pub fn from_u32(code: u32) -> Option<Self> {
+		match code {
+			1000 => Some(Self::InvalidInstruction),
+			1001 => Some(Self::IncorrectPaymentAmount),
+			1002 => Some(Self::RentAlreadyFullyPaid),
+			1003 => Some(Self::SecurityAlreadyDeposited),
+			1004 => Some(Self::RentAgreementTerminated),
+			1005 => Some(Self::InvalidAgreementStatus),
+			1006 => Some(Self::InvalidInstructionParameter),
+			1007 => Some(Self::EscrowInsolvent),
+			1008 => Some(Self::RefundLocked),
+			1009 => Some(Self::ProgramPaused),
+			1010 => Some(Self::CorruptState),
+			1011 => Some(Self::InvalidAccountOwner),
+			1012 => Some(Self::ArithmeticOverflow),
+			1013 => Some(Self::OwnerMismatch),
+			1014 => Some(Self::TenantMismatch),
+			1015 => Some(Self::ClockUnavailable),
+			1016 => Some(Self::PaymentTooEarly),
+			1017 => Some(Self::AgreementFinalized),
+			1018 => Some(Self::AgreementSealed),
+			_ => None,
+		}
+	}
 }
 
 impl From<TrustedPropertiesError> for ProgramError {
@@ -41,3 +119,41 @@ impl From<TrustedPropertiesError> for ProgramError {
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_error_codes_round_trip() {
+		let codes = [
+			(TrustedPropertiesError::InvalidInstruction, 1000),
+			(TrustedPropertiesError::IncorrectPaymentAmount, 1001),
+			(TrustedPropertiesError::RentAlreadyFullyPaid, 1002),
+			(TrustedPropertiesError::SecurityAlreadyDeposited, 1003),
+			(TrustedPropertiesError::RentAgreementTerminated, 1004),
+			(TrustedPropertiesError::InvalidAgreementStatus, 1005),
+			(TrustedPropertiesError::InvalidInstructionParameter, 1006),
+			(TrustedPropertiesError::EscrowInsolvent, 1007),
+			(TrustedPropertiesError::RefundLocked, 1008),
+			(TrustedPropertiesError::ProgramPaused, 1009),
+			(TrustedPropertiesError::CorruptState, 1010),
+			(TrustedPropertiesError::InvalidAccountOwner, 1011),
+			(TrustedPropertiesError::ArithmeticOverflow, 1012),
+			(TrustedPropertiesError::OwnerMismatch, 1013),
+			(TrustedPropertiesError::TenantMismatch, 1014),
+			(TrustedPropertiesError::ClockUnavailable, 1015),
+			(TrustedPropertiesError::PaymentTooEarly, 1016),
+			(TrustedPropertiesError::AgreementFinalized, 1017),
+			(TrustedPropertiesError::AgreementSealed, 1018),
+		];
+
+		for (error, code) in codes {
+			assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+			assert_eq!(TrustedPropertiesError::from_u32(code), Some(error));
+		}
+
+		assert_eq!(TrustedPropertiesError::from_u32(999), None);
+		assert_eq!(TrustedPropertiesError::from_u32(1019), None);
+	}
+}
@@ -0,0 +1,47 @@
+/// events.rs -> Anchor-compatible event logs, for indexers built on Anchor's `emit!` format
+///
+/// Only compiled with the `anchor-events` feature, so that the default build logs nothing extra.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{hash::hashv, log::sol_log_data, pubkey::Pubkey};
+
+
+/// An event the program can emit, named as Anchor clients expect to find it in the program IDL
+pub trait AnchorEvent: BorshSerialize {
+	const NAME: &'static str;
+
+	/// First 8 bytes of `sha256("event:<NAME>")`, prefixing the serialized event as in Anchor
+	fn discriminator() -> [u8; 8] {
+		let mut discriminator = [0u8; 8];
+		discriminator.copy_from_slice(&hashv(&[b"event:", Self::NAME.as_bytes()]).to_bytes()[..8]);
+		discriminator
+	}
+
+	/// Discriminator followed by the Borsh serialized event, as logged (base64 encoded) by `emit`
+	fn data(&self) -> Vec<u8> {
+		let mut data = Self::discriminator().to_vec();
+		data.extend(self.try_to_vec().unwrap_or_default());
+		data
+	}
+}
+
+/// Log the event as a `Program data: <base64>` line, like Anchor's `emit!`
+pub fn emit<E: AnchorEvent>(event: &E) {
+	sol_log_data(&[&event.data()]);
+}
+
+
+/// A monthly rent payment was made (or recorded), by any of the payment instructions
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct RentPaid {
+	pub agreement_pubkey: Pubkey,
+	pub payment_number: u8,
+	/// Lamports (or tokens of the payment mint) paid, 0 when the payment is recorded without any transfer (self-rent, external payment)
+	pub amount: u64,
+	pub remaining_payments: u8,
+	pub event_seq: u64,
+}
+
+impl AnchorEvent for RentPaid {
+	const NAME: &'static str = "RentPaid";
+}
@@ -3,10 +3,18 @@
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
 
+pub mod accounts;
 pub mod instruction;
 pub mod processor;
 pub mod state;
 pub mod error;
+pub mod decode;
+
+#[cfg(feature = "schema")]
+pub mod schema;
+
+#[cfg(feature = "anchor-events")]
+pub mod events;
 
 // Error Logging
 // macro_rules! loge {
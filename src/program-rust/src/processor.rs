@@ -2,24 +2,455 @@
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
-	account_info::{next_account_info, AccountInfo},
+	account_info::AccountInfo,
+	bpf_loader_upgradeable,
 	entrypoint::ProgramResult,
 	msg,
-	program::invoke,
+	program::{invoke, invoke_signed, set_return_data},
 	program_error::ProgramError,
-	program_pack::IsInitialized,
+	program_pack::{IsInitialized, Pack},
 	pubkey::Pubkey,
-	system_instruction,
-	sysvar::{rent::Rent, Sysvar},
+	system_instruction, system_program,
+	sysvar::{self, clock::Clock, rent::Rent, Sysvar},
 };
+use spl_token::state::Account as TokenAccount;
+use std::convert::TryFrom;
 
 use crate::{
+	accounts::{check_writable_state, next_account, ClaimRefundAccounts, DepositSecurityAccounts, PayRentAccounts, RefundSecurityDepositAccounts},
 	error::TrustedPropertiesError,
-	instruction::TrustedPropertiesInstruction,
-	state::{AgreementStatus, RentAgreementAccount},
+	instruction::{InitParams, TrustedPropertiesInstruction},
+	state::{AgreementStatus, CrankOverdueSummary, Lamports, Ledger, NextDue, PortfolioSummary, ProgramConfig, ProgramConfigV1, RentAgreementAccount, SecurityEscrowAccount, TenantReputation, agreement_rent_exempt_minimum, compute_settlement, escrow_rent_exempt_minimum, split_bps, CONFIG_SEED, ESCROW_SEED, EXTENSION_REQUEST_WINDOW_DAYS, MAX_AGREEMENT_YEAR, MAX_BATCH_SIZE, MAX_CO_OWNERS, MAX_PLATFORM_FEE_BPS, MIN_AGREEMENT_YEAR},
 };
 
 
+/// Move lamports out of an account owned by this program (eg: the security escrow).
+/// The program can debit its own accounts directly, without invoking the system program.
+fn transfer_from_program_account(from: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+	let from_balance = from.lamports()
+		.checked_sub(amount)
+		.ok_or(TrustedPropertiesError::EscrowInsolvent)?;
+	let to_balance = to.lamports()
+		.checked_add(amount)
+		.ok_or(ProgramError::InvalidArgument)?;
+
+	**from.try_borrow_mut_lamports()? = from_balance;
+	**to.try_borrow_mut_lamports()? = to_balance;
+
+	Ok(())
+}
+
+
+/// Ensure the account passed as the system program is the real one, before invoking it.
+/// Otherwise the caller could substitute any program of their own as the CPI target.
+fn check_system_program(system_program_account: &AccountInfo) -> ProgramResult {
+	if *system_program_account.key != system_program::id() {
+		msg!("[TrustedProperties] ERROR: System program account is not the system program: {}", system_program_account.key);
+		return Err(ProgramError::IncorrectProgramId);
+	}
+	Ok(())
+}
+
+
+/// Transfer lamports (via the system program) from a signing payer, logging the payer's balance read immediately before & after the transfer.
+fn transfer_with_balance_log<'a>(
+	from: &AccountInfo<'a>,
+	to: &AccountInfo<'a>,
+	system_program: &AccountInfo<'a>,
+	amount: u64,
+	label: &str,
+) -> ProgramResult {
+	check_system_program(system_program)?;
+
+	let balance_before = from.lamports();
+
+	invoke(
+		&system_instruction::transfer(from.key, to.key, amount),
+		&[
+			system_program.clone(),
+			to.clone(),
+			from.clone(),
+		],
+	)?;
+
+	let balance_after = from.lamports();
+	msg!("[TrustedProperties] {}: paid {}, balance {} → {}", label, balance_before.saturating_sub(balance_after), balance_before, balance_after);
+
+	Ok(())
+}
+
+
+/// Store the (Borsh serialized) state into the account data.
+/// Checks the serialized length against the account's allocated data first, so that a state that outgrew its account
+/// fails cleanly instead of being written partially.
+fn pack_into<T: BorshSerialize>(state: &T, data: &mut [u8]) -> ProgramResult {
+	let bytes = state.try_to_vec()?;
+	if bytes.len() > data.len() {
+		msg!("[TrustedProperties] ERROR: Account data too small: state needs {} bytes, account has {}", bytes.len(), data.len());
+		return Err(ProgramError::AccountDataTooSmall);
+	}
+
+	data[..bytes.len()].copy_from_slice(&bytes);
+	Ok(())
+}
+
+
+/// Current unix timestamp, from the Clock sysvar.
+/// Fails with a descriptive `ClockUnavailable` (rather than an opaque sysvar error) on a runtime without the sysvar.
+fn clock_now() -> Result<i64, ProgramError> {
+	Clock::get().map(|clock| clock.unix_timestamp).map_err(|err| {
+		msg!("[TrustedProperties] ERROR: Clock sysvar unavailable: {:?}", err);
+		TrustedPropertiesError::ClockUnavailable.into()
+	})
+}
+
+/// Clock-optional mode: the current unix timestamp, or `None` when the Clock sysvar is unavailable.
+/// Lets a payment skip its time-dependent parts (late fee & late tracking) instead of aborting the whole transaction.
+fn clock_now_optional() -> Option<i64> {
+	match Clock::get() {
+		Ok(clock) => Some(clock.unix_timestamp),
+		Err(_) => {
+			msg!("[TrustedProperties] WARNING: Clock sysvar unavailable: overdue & late payment logic skipped");
+			None
+		}
+	}
+}
+
+/// Completion timestamp of an agreement whose last payment is made (or waived) now.
+/// In clock-optional mode, falls back to the due date of the last payment of the term instead of aborting the completion.
+fn completion_ts(rent_data: &RentAgreementAccount) -> i64 {
+	clock_now_optional().unwrap_or_else(|| {
+		let due_ts = rent_data.due_ts_for_payment(rent_data.duration);
+		msg!("[TrustedProperties] WARNING: Completion time recorded as the due date of the last payment: {}", due_ts);
+		due_ts
+	})
+}
+
+/// Amount due for the n-th payment right now: with the late fee when overdue, or the plain rent in clock-optional mode
+fn amount_due_now(rent_data: &RentAgreementAccount, payment_number: u8) -> Result<u64, ProgramError> {
+	match clock_now_optional() {
+		Some(now) => rent_data.amount_due_for_payment(payment_number, now),
+		None => rent_data.rent_for_payment(payment_number),
+	}
+}
+
+/// Platform fee & owner's share of the n-th payment of `amount` (its rent, with the late fee when overdue).
+/// The fee is charged on the rent of the payment only: the late fee goes to the owner in full.
+fn platform_fee_split(rent_data: &RentAgreementAccount, payment_number: u8, amount: u64) -> Result<(u64, u64), ProgramError> {
+	let (platform_fee, _) = split_bps(rent_data.rent_for_payment(payment_number)?, rent_data.platform_fee_bps);
+	let owner_share = amount
+		.checked_sub(platform_fee)
+		.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+	Ok((platform_fee, owner_share))
+}
+
+/// The platform wallet account passed to a payment with a platform fee: must be the one used during the agreement initialization
+fn platform_wallet_for<'a, 'b>(platform_wallet_account: Option<&'a AccountInfo<'b>>, rent_data: &RentAgreementAccount) -> Result<&'a AccountInfo<'b>, ProgramError> {
+	match platform_wallet_account {
+		Some(account) if *account.key == rent_data.platform_wallet => Ok(account),
+		_ => {
+			msg!("[TrustedProperties] ERROR: Platform wallet account does not match the one used during agreement initialization: {}", rent_data.platform_wallet);
+			Err(ProgramError::InvalidAccountData)
+		}
+	}
+}
+
+
+/// Serialize the Program Config into its account data.
+/// A v1 config account (too small for the governable parameters) only gets the fields of its layout, so that it can still be paused.
+fn pack_config(config: &ProgramConfig, data: &mut [u8]) -> ProgramResult {
+	if data.len() == ProgramConfig::V1_LEN {
+		return pack_into(&ProgramConfigV1 { paused: config.paused, authority: config.authority }, data);
+	}
+	pack_into(config, data)
+}
+
+
+/// Money can only move (rent payments, escrow top-ups, extensions) while the agreement is financially active.
+/// Logs & returns the error specific to the status the agreement is in otherwise.
+fn require_financially_active(rent_data: &RentAgreementAccount) -> ProgramResult {
+	if rent_data.is_financially_active() {
+		return Ok(());
+	}
+
+	require_not_finalized(rent_data)?;
+
+	if rent_data.is_disputed() {
+		msg!("[TrustedProperties] ERROR: Rent agreement is on hold due to a dispute");
+	} else if rent_data.is_suspended() {
+		msg!("[TrustedProperties] ERROR: Rent agreement is suspended");
+	} else {
+		msg!("[TrustedProperties] ERROR: Agreement is not active (status: {})", rent_data.status);
+	}
+	Err(TrustedPropertiesError::InvalidAgreementStatus.into())
+}
+
+
+/// A payment must be for exactly the amount expected by the agreement (eg: the rent due or the agreed security deposit).
+/// Logs both amounts uniformly for every payment path.
+fn require_exact_amount(provided: u64, expected: u64) -> ProgramResult {
+	if provided != expected {
+		msg!("[TrustedProperties] ERROR: Amount provided ({}) does not match the amount expected ({})", provided, expected);
+		return Err(TrustedPropertiesError::IncorrectPaymentAmount.into());
+	}
+	Ok(())
+}
+
+
+/// Load the state of an initialized Rent Agreement account from its data, shared by all the handlers.
+/// Fails with `InvalidAccountData` for a data size of no known layout, or `UninitializedAccount`.
+fn agreement_from_data(data: &[u8]) -> Result<RentAgreementAccount, ProgramError> {
+
+	// Note: the structure of the data state must match the `space` the client used to create the account
+	let rent_agreement_data = RentAgreementAccount::try_from_slice_versioned(data);
+	if rent_agreement_data.is_err() {
+		msg!("[TrustedProperties] ERROR: Rent agreement account data size incorrect: {}", data.len());
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	let rent_data = rent_agreement_data.unwrap();
+	if !rent_data.is_initialized() {
+		msg!("[TrustedProperties] ERROR: Invalid agreement: Rent agreement account not initialized.");
+		return Err(ProgramError::UninitializedAccount);
+	}
+
+	Ok(rent_data)
+}
+
+/// Load the state of an initialized Rent Agreement account (borrowing its data only while deserializing)
+fn load_agreement(rent_agreement_account: &AccountInfo) -> Result<RentAgreementAccount, ProgramError> {
+	agreement_from_data(&rent_agreement_account.data.borrow())
+}
+
+/// Load the state of the agreement's Security Escrow account, which must hold the deposit of this agreement only:
+/// bound to the agreement by its deposit, or still fresh while the deposit is pending.
+/// Otherwise another agreement naming the same escrow could pay out (or add to) this agreement's deposit.
+fn load_escrow(escrow_account: &AccountInfo, rent_agreement_account: &AccountInfo, rent_data: &RentAgreementAccount) -> Result<SecurityEscrowAccount, ProgramError> {
+	let escrow_data = SecurityEscrowAccount::try_from_slice(&escrow_account.data.borrow());
+	if escrow_data.is_err() {
+		msg!("[TrustedProperties] Security escrow account data size incorrect: {}", escrow_account.try_data_len()?);
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	let escrow = escrow_data.unwrap();
+	let is_bound = escrow.agreement_pubkey == *rent_agreement_account.key;
+	let is_claimable = escrow.is_fresh() && rent_data.is_security_deposit_pending();
+	if !(is_bound || is_claimable) {
+		msg!("[TrustedProperties] ERROR: Security escrow account holds the deposit of another agreement: {}", escrow.agreement_pubkey);
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	Ok(escrow)
+}
+
+
+/// Uniform guard of the mutating instructions: a finalized (completed or terminated) agreement only accepts
+/// refunding (or claiming) the security deposit, renewing it, settling a penalty shortfall, recording the move-out report & the read instructions.
+fn require_not_finalized(rent_data: &RentAgreementAccount) -> ProgramResult {
+	if rent_data.is_finalized() {
+		msg!("[TrustedProperties] ERROR: Rent agreement is finalized (status: {}): only refunds, renewal & reads are allowed", rent_data.status_label());
+		return Err(TrustedPropertiesError::AgreementFinalized.into());
+	}
+	Ok(())
+}
+
+
+/// A sealed agreement is a permanent record: guards the instructions still changing a finalized agreement.
+fn require_not_sealed(rent_data: &RentAgreementAccount) -> ProgramResult {
+	if rent_data.is_sealed {
+		msg!("[TrustedProperties] ERROR: Rent agreement is sealed as a permanent record and cannot be changed");
+		return Err(TrustedPropertiesError::AgreementSealed.into());
+	}
+	Ok(())
+}
+
+
+/// Ensure the agreement account is still rent-exempt for its current data length before writing to it.
+/// Accounts allocated (& funded) for an older, smaller layout may no longer be once the layout grows.
+fn check_rent_exempt(rent_agreement_account: &AccountInfo) -> ProgramResult {
+	let minimum_balance = Rent::get()?.minimum_balance(rent_agreement_account.data_len());
+	if rent_agreement_account.lamports() < minimum_balance {
+		msg!("[TrustedProperties] ERROR: Rent Agreement account not rent exempt for its data length ({}). Balance: {}, minimum: {}", rent_agreement_account.data_len(), rent_agreement_account.lamports(), minimum_balance);
+		return Err(ProgramError::AccountNotRentExempt);
+	}
+	Ok(())
+}
+
+
+/// On completion, warn when the escrow no longer holds the deposit owed back to the tenant (eg: drained by penalties),
+/// surfacing the insolvency before the tenant attempts the refund. Only logs: the completion itself stands.
+/// Every instruction that can complete an agreement takes its escrow account for this, found by its key among `accounts`.
+fn warn_if_refund_uncovered(accounts: &[AccountInfo], rent_agreement_account: &AccountInfo, rent_data: &RentAgreementAccount) -> ProgramResult {
+	if !rent_data.is_completed() {
+		return Ok(());
+	}
+
+	let escrow_account = match accounts.iter().find(|account| *account.key == rent_data.security_escrow_pubkey) {
+		Some(account) => account,
+		None => {
+			msg!("[TrustedProperties] ERROR: Security escrow account is required to complete the agreement: {}", rent_data.security_escrow_pubkey);
+			return Err(ProgramError::NotEnoughAccountKeys);
+		}
+	};
+
+	let remaining_deposit = u64::from(load_escrow(escrow_account, rent_agreement_account, rent_data)?.remaining_deposit);
+	let available_balance = escrow_account.lamports().saturating_sub(escrow_rent_exempt_minimum(&Rent::get()?));
+	if remaining_deposit > available_balance {
+		msg!("[TrustedProperties] WARNING: Escrow does not cover the pending refund. Remaining deposit: {}, available balance: {}, shortfall: {}", remaining_deposit, available_balance, remaining_deposit - available_balance);
+	}
+
+	Ok(())
+}
+
+
+/// Emit a `RentPaid` event (with the `anchor-events` feature only) for a monthly payment recorded by any of the payment instructions.
+/// Called once the payment is recorded & the event sequence number advanced, so the event carries the stored state.
+#[cfg_attr(not(feature = "anchor-events"), allow(unused_variables))]
+fn emit_rent_paid(rent_agreement_account: &AccountInfo, rent_data: &RentAgreementAccount, payment_number: u8, amount: u64) {
+	#[cfg(feature = "anchor-events")]
+	crate::events::emit(&crate::events::RentPaid {
+		agreement_pubkey: *rent_agreement_account.key,
+		payment_number,
+		amount,
+		remaining_payments: rent_data.remaining_payments,
+		event_seq: rent_data.event_seq,
+	});
+}
+
+
+/// The account must sign (`MissingRequiredSignature`) and be the expected party of the agreement (`role_err`),
+/// eg: the owner or tenant stored at the initialization.
+fn require_signing_party(account: &AccountInfo, expected: &Pubkey, role_err: TrustedPropertiesError) -> ProgramResult {
+	if !account.is_signer {
+		msg!("[TrustedProperties] ERROR: {} must sign the instruction", account.key);
+		return Err(ProgramError::MissingRequiredSignature);
+	}
+
+	if account.key != expected {
+		msg!("[TrustedProperties] ERROR: {} does not match the party of the agreement: {}", account.key, expected);
+		return Err(role_err.into());
+	}
+
+	Ok(())
+}
+
+
+/// Restrict a read to the parties of the agreement: the account must be the signing owner or tenant.
+fn check_party_signer(party_account: &AccountInfo, rent_data: &RentAgreementAccount) -> ProgramResult {
+	if !party_account.is_signer {
+		msg!("[TrustedProperties] ERROR: Owner or Tenant must sign to read the agreement");
+		return Err(ProgramError::MissingRequiredSignature);
+	}
+
+	if *party_account.key != rent_data.owner_pubkey && *party_account.key != rent_data.tenant_pubkey {
+		msg!("[TrustedProperties] ERROR: Only the owner or the tenant of the agreement can read it: {}", party_account.key);
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	Ok(())
+}
+
+
+/// The term (start date plus duration) must start & end within the supported year window,
+/// so that the end date of the agreement is always representable.
+fn check_term_years(start_month: u8, start_year: u16, duration: u8) -> ProgramResult {
+	let months = (start_month as u32).saturating_sub(1) + (duration as u32).saturating_sub(1);
+	let end_year = start_year as u32 + months / 12;
+	if start_year < MIN_AGREEMENT_YEAR || end_year > MAX_AGREEMENT_YEAR as u32 {
+		msg!("[TrustedProperties] ERROR: Agreement term ({} to {}) must be within the years {} to {}", start_year, end_year, MIN_AGREEMENT_YEAR, MAX_AGREEMENT_YEAR);
+		return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+	}
+	Ok(())
+}
+
+
+/// The terms of a new (or renewed) agreement: a non-zero rent & duration within the program config limits,
+/// a security deposit of at least the configured months of rent & a term within the supported years.
+fn check_terms(config: &ProgramConfig, rent_amount: u64, security_deposit: u64, duration: u8, start_month: u8, start_year: u16) -> ProgramResult {
+	if rent_amount == 0 {
+		msg!("[TrustedProperties] ERROR: Rent amount must not be zero");
+		return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+	}
+
+	if duration == 0 || !(1..=12).contains(&start_month) {
+		msg!("[TrustedProperties] ERROR: Invalid agreement term: duration {}, start month {}", duration, start_month);
+		return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+	}
+
+	if duration > config.max_duration {
+		msg!("[TrustedProperties] ERROR: Duration ({} months) must not exceed {} months", duration, config.max_duration);
+		return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+	}
+
+	let min_deposit = rent_amount
+		.checked_mul(config.min_deposit_months as u64)
+		.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+	if security_deposit < min_deposit {
+		msg!("[TrustedProperties] ERROR: Security deposit ({}) must be at least {} months of rent ({})", security_deposit, config.min_deposit_months, min_deposit);
+		return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+	}
+
+	check_term_years(start_month, start_year, duration)
+}
+
+
+/// Reject the initialization of new agreements while the program is paused, returning the config in effect.
+/// The Program Config account only exists after the first `SetPaused`; until then the program is not paused & the defaults apply.
+fn check_not_paused(config_account: &AccountInfo, program_id: &Pubkey) -> Result<ProgramConfig, ProgramError> {
+	let (config_pda, _) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+	if config_pda != *config_account.key {
+		msg!("[TrustedProperties] ERROR: Program config account does not match the derived config PDA: {}", config_pda);
+		return Err(ProgramError::InvalidSeeds);
+	}
+
+	if config_account.data_is_empty() {
+		return Ok(ProgramConfig::default());
+	}
+
+	if config_account.owner != program_id {
+		msg!("[TrustedProperties] ERROR: Program config account must be owned by this program");
+		return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+	}
+
+	let config_data = ProgramConfig::try_from_slice_versioned(&config_account.data.borrow());
+	if config_data.is_err() {
+		msg!("[TrustedProperties] ERROR: Program config account data size incorrect: {}", config_account.try_data_len()?);
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	let config = config_data.unwrap();
+	if config.paused {
+		msg!("[TrustedProperties] ERROR: Program is paused: new agreements cannot be initialized");
+		return Err(TrustedPropertiesError::ProgramPaused.into());
+	}
+
+	Ok(config)
+}
+
+
+/// Upgrade authority of this program, read from its ProgramData account (`None` once the program is made immutable).
+fn program_upgrade_authority(program_data_account: &AccountInfo, program_id: &Pubkey) -> Result<Option<Pubkey>, ProgramError> {
+	let (program_data_address, _) = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+	if *program_data_account.key != program_data_address || *program_data_account.owner != bpf_loader_upgradeable::id() {
+		msg!("[TrustedProperties] ERROR: Account is not the ProgramData account of this program: {}", program_data_address);
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	// ProgramData layout (bincode): u32 variant (3), u64 deployment slot, Option<Pubkey> upgrade authority
+	let data = program_data_account.try_borrow_data()?;
+	if data.get(0..4) != Some(&3u32.to_le_bytes()[..]) {
+		msg!("[TrustedProperties] ERROR: ProgramData account holds an unexpected loader state");
+		return Err(ProgramError::InvalidAccountData);
+	}
+
+	match data.get(12) {
+		Some(0) => Ok(None),
+		Some(1) => data.get(13..45).map(|key| Some(Pubkey::new(key))).ok_or(ProgramError::InvalidAccountData),
+		_ => Err(ProgramError::InvalidAccountData),
+	}
+}
+
+
 pub struct Processor;
 
 impl Processor {
@@ -31,419 +462,5046 @@ impl Processor {
 	/// @param instruction_data The additional transaction parameters passed to the program as byte array.
 	pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
 
-		msg!("[TrustedProperties] Rust Program Entrypoint.");
+		msg!("[TrustedProperties] Rust Program Entrypoint.");
+
+		// A common client mistake: forgetting to attach the instruction data. Tell it apart from malformed data.
+		if instruction_data.is_empty() {
+			msg!("[TrustedProperties] ERROR: No instruction data provided");
+			return Err(TrustedPropertiesError::InvalidInstruction.into());
+		}
+
+		// Log the attempted instruction before unpacking, so that a failed unpack still shows which one it was
+		let tag = instruction_data[0];
+		msg!("[TrustedProperties] Instruction tag: {} ({})", tag, TrustedPropertiesInstruction::tag_name(tag).unwrap_or("unknown"));
+
+		let instruction = TrustedPropertiesInstruction::unpack(instruction_data)?;
+		match instruction {
+			// Initialize the rent-contract
+			TrustedPropertiesInstruction::InitializeRentContract(params) => Self::initialize_rent_contract(accounts, program_id, &params),
+
+			// Refund the security deposit (from escrow to tenant) after completion
+			TrustedPropertiesInstruction::RefundSecurityDeposit {} => Self::refund_security_deposit(accounts, program_id),
+
+			// Pay rent in SPL tokens (from tenant to owner)
+			TrustedPropertiesInstruction::PayRentToken {} => Self::pay_rent_token(accounts, program_id),
+
+			// Settle the penalty shortfall not covered by the security deposit (from tenant to owner)
+			TrustedPropertiesInstruction::SettleShortfall {} => Self::settle_shortfall(accounts, program_id),
+
+			// Trigger the next auto-pay rent payment (from escrow to owner, by the authorized cranker)
+			TrustedPropertiesInstruction::CrankAutoPay {} => Self::crank_auto_pay(accounts, program_id),
+
+			// Set the co-owners of the property (by Owner)
+			TrustedPropertiesInstruction::SetCoOwners { co_owners } => Self::set_co_owners(accounts, program_id, &co_owners),
+
+			// Pay first-time security_deposit amount (from tenant to escrow) & confirm the agreement
+			TrustedPropertiesInstruction::DepositSecurity { security_deposit_amount } => Self::deposit_security(accounts, program_id, security_deposit_amount),
+
+			// Pay rent from (tenant to owner)
+			TrustedPropertiesInstruction::PayRent { rent_amount, op_nonce } => Self::pay_rent(accounts, program_id, rent_amount, op_nonce),
+
+			// Terminate the contract early
+			TrustedPropertiesInstruction::TerminateEarly {} => Self::terminate_early(accounts, program_id),
+
+			// Request to extend the contract duration (by Tenant)
+			TrustedPropertiesInstruction::RequestContractDurationExtension { extension_duration } => Self::extend_contract_duration_request(accounts, program_id, extension_duration),
+
+			// Confirm to extend the contract duration (by Owner)
+			TrustedPropertiesInstruction::ConfirmContractDurationExtension { extension_duration } => Self::extend_contract_duration_confirm(accounts, program_id, extension_duration, false),
+
+			// Top up the security deposit escrow (from tenant to escrow)
+			TrustedPropertiesInstruction::TopUpEscrow { amount } => Self::top_up_escrow(accounts, program_id, amount),
+
+			// Raise a dispute (by Owner or Tenant)
+			TrustedPropertiesInstruction::RaiseDispute {} => Self::raise_dispute(accounts, program_id),
+
+			// Resolve a dispute (by both Owner and Tenant)
+			TrustedPropertiesInstruction::ResolveDispute {} => Self::resolve_dispute(accounts, program_id),
+
+			// Verify that the escrow holds the tracked security deposit
+			TrustedPropertiesInstruction::VerifyEscrowSolvency {} => Self::verify_escrow_solvency(accounts, program_id),
+
+			// Initialize the rent-contract & pay the security_deposit amount (from tenant to escrow) at once
+			TrustedPropertiesInstruction::InitializeAndDeposit(params) => Self::initialize_and_deposit(accounts, program_id, &params),
+
+			// Get the tenant's reputation summary
+			TrustedPropertiesInstruction::GetTenantReputation {} => Self::get_tenant_reputation(accounts, program_id),
+
+			// Terminate the contract early with a prorated rent for the current month
+			TrustedPropertiesInstruction::TerminateWithProration { days_occupied } => Self::terminate_with_proration(accounts, program_id, days_occupied),
+
+			// Get the financial ledger of the agreement
+			TrustedPropertiesInstruction::GetLedger { require_party } => Self::get_ledger(accounts, program_id, require_party),
+
+			// Migrate the security deposit to a new escrow PDA (by both Owner and Tenant)
+			TrustedPropertiesInstruction::MigrateEscrow {} => Self::migrate_escrow(accounts, program_id),
+
+			// Pause (or resume) the initialization of new agreements (by the program authority)
+			TrustedPropertiesInstruction::SetPaused { paused } => Self::set_paused(accounts, program_id, paused),
+
+			// Record the hash of a move-in (by Tenant) or move-out (by Owner) condition report
+			TrustedPropertiesInstruction::RecordConditionReport { report_hash, is_movein } => Self::record_condition_report(accounts, program_id, report_hash, is_movein),
+
+			// Authorize a cranker to trigger the rent payments (by Tenant)
+			TrustedPropertiesInstruction::AuthorizeAutoPay { max_payments } => Self::authorize_auto_pay(accounts, program_id, max_payments),
+
+			// Pay rent split between the owner & co-owners (from tenant to each payee)
+			TrustedPropertiesInstruction::PayRentSplit { splits } => Self::pay_rent_split(accounts, program_id, &splits),
+
+			// Waive the rent of some months (by Owner)
+			TrustedPropertiesInstruction::WaiveRent { months } => Self::waive_rent(accounts, program_id, months),
+
+			// Adjust the security deposit amount (by both Owner and Tenant)
+			TrustedPropertiesInstruction::AdjustDeposit { new_deposit } => Self::adjust_deposit(accounts, program_id, new_deposit),
+
+			// Get the due date of the next rent payment
+			TrustedPropertiesInstruction::GetNextDue {} => Self::get_next_due(accounts, program_id),
+
+			// Assign the tenancy to a new tenant (by both Owner and Tenant)
+			TrustedPropertiesInstruction::AssignTenancy { new_tenant_pubkey, settle_deposit } => Self::assign_tenancy(accounts, program_id, new_tenant_pubkey, settle_deposit),
+
+			// Sum the figures across the agreements of an owner (by Owner)
+			TrustedPropertiesInstruction::AccumulateTvl {} => Self::accumulate_tvl(accounts, program_id),
+
+			// Report a breach of the maximum occupancy clause (by Owner)
+			TrustedPropertiesInstruction::ReportOccupancyBreach {} => Self::report_occupancy_breach(accounts, program_id),
+
+			// Suspend the agreement (by both Owner and Tenant)
+			TrustedPropertiesInstruction::SuspendAgreement {} => Self::set_suspended(accounts, program_id, true),
+
+			// Resume a suspended agreement (by both Owner and Tenant)
+			TrustedPropertiesInstruction::ResumeAgreement {} => Self::set_suspended(accounts, program_id, false),
+
+			// Claim the security deposit refund the owner did not make (by Tenant)
+			TrustedPropertiesInstruction::ClaimRefund {} => Self::claim_refund(accounts, program_id),
+
+			// Get the payment schedule of the agreement
+			TrustedPropertiesInstruction::GetSchedule { require_party } => Self::get_schedule(accounts, program_id, require_party),
+
+			// Renew a completed agreement into a fresh term (by both Owner and Tenant)
+			TrustedPropertiesInstruction::RenewAgreement { duration, rent_amount, start_month, start_year } => Self::renew_agreement(accounts, program_id, duration, rent_amount, start_month, start_year),
+
+			// Resolve a dispute by splitting the deposit between the tenant & owner (by Arbiter)
+			TrustedPropertiesInstruction::ArbiterResolve { tenant_share_bps, terminate } => Self::arbiter_resolve(accounts, program_id, tenant_share_bps, terminate),
+
+			// Record a payment made outside the program (by both Owner and Tenant)
+			TrustedPropertiesInstruction::RecordExternalPayment { reference } => Self::record_external_payment(accounts, program_id, reference),
+
+			// Confirm part of the requested extension of the contract duration (by Owner)
+			TrustedPropertiesInstruction::ConfirmExtensionPartial { approved_duration } => Self::extend_contract_duration_confirm(accounts, program_id, approved_duration, true),
+
+			// Check whether a public-key is a party to the agreement
+			TrustedPropertiesInstruction::CheckParty { key } => Self::check_party(accounts, program_id, &key),
+
+			// Initialize several agreements at once (by Owner)
+			TrustedPropertiesInstruction::InitializeBatch { agreements } => Self::initialize_batch(accounts, program_id, &agreements),
+
+			// Force-resolve a stuck agreement, as a last resort (by the program authority)
+			TrustedPropertiesInstruction::AdminForceResolve { final_status } => Self::admin_force_resolve(accounts, program_id, final_status),
+
+			// Get the program-wide parameters in effect
+			TrustedPropertiesInstruction::GetConfig {} => Self::get_config(accounts, program_id),
+
+			// Update the program-wide parameters (by the program authority)
+			TrustedPropertiesInstruction::SetConfig { max_platform_fee_bps, max_duration, min_deposit_months } => Self::set_config(accounts, program_id, max_platform_fee_bps, max_duration, min_deposit_months),
+
+			// Merge two agreements between the same parties (by both Owner and Tenant)
+			TrustedPropertiesInstruction::MergeAgreements {} => Self::merge_agreements(accounts, program_id),
+
+			// Seal a finalized agreement as a permanent record (by both Owner and Tenant)
+			TrustedPropertiesInstruction::FinalizeRecord {} => Self::finalize_record(accounts, program_id),
+
+			// Pay the overdue rent of a tenant's agreements (by the Tenant)
+			TrustedPropertiesInstruction::CrankOverdue {} => Self::crank_overdue(accounts, program_id),
+		}
+	}
+
+
+	/// Initialize the Contract Account for the rent agreement
+	fn initialize_rent_contract(accounts: &[AccountInfo], program_id: &Pubkey, params: &InitParams) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		// Whoever created the account (a wallet, or a parent program via CPI), it must have been assigned to this program
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] ERROR: Rent Agreement account must be owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		// Under CPI, a PDA owner is a signer when the invoking program signs for it with `invoke_signed`
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		if !owner_account.is_signer {
+			msg!("[TrustedProperties] ERROR: Owner must sign the Rent Agreement initialization");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		if *owner_account.key != params.owner_pubkey {
+			msg!("[TrustedProperties] ERROR: Owner account does not match the owner's public-key (owner_pubkey) of the agreement");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// The Rent sysvar account is optional: read the sysvar directly when it is not passed
+		let solana_rent = &match accounts_iter.as_slice().first() {
+			Some(account) if sysvar::rent::check_id(account.key) => Rent::from_account_info(next_account(accounts_iter, "rent sysvar account")?)?,
+			_ => Rent::get()?,
+		};
+
+		let config = check_not_paused(next_account(accounts_iter, "program config account")?, program_id)?;
+
+		if params.first_month_proration_bps > 10_000 {
+			msg!("[TrustedProperties] ERROR: First month proration ({} bps) must not exceed 10000 bps", params.first_month_proration_bps);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		if params.platform_fee_bps > config.max_platform_fee_bps {
+			msg!("[TrustedProperties] ERROR: Platform fee ({} bps) must not exceed {} bps", params.platform_fee_bps, config.max_platform_fee_bps);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		check_terms(&config, params.rent_amount, params.security_deposit, params.duration, params.start_month, params.start_year)?;
+
+		// Make sure this account is rent exempt
+		// Program owners can maintain a minimum amount of Lamports to keep the program rent-free.
+		if rent_agreement_account.lamports() < agreement_rent_exempt_minimum(solana_rent) {
+			msg!("[TrustedProperties] ERROR: Rent Agreement account not rent exempt. Balance: {}, minimum: {}", rent_agreement_account.lamports(), agreement_rent_exempt_minimum(solana_rent));
+			return Err(ProgramError::AccountNotRentExempt);
+		}
+
+		if rent_agreement_account.data_len() != RentAgreementAccount::LEN {
+			msg!("[TrustedProperties] ERROR: Rent Agreement account data size ({}) must be {} bytes", rent_agreement_account.data_len(), RentAgreementAccount::LEN);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// A freshly created account is entirely zeroed. Any other byte may be left-over (or crafted) state.
+		if rent_agreement_account.data.borrow().iter().any(|byte| *byte != 0) {
+			msg!("[TrustedProperties] ERROR: Rent Agreement account data is not empty");
+			return Err(ProgramError::AccountAlreadyInitialized);
+		}
+
+		// Initialize the Rent Agreement Account with the initial data
+		// Note: the structure of the data state must match the `space` reserved when account created
+		let mut rent_data = RentAgreementAccount::new(
+			params.owner_pubkey,
+			params.tenant_pubkey,
+			params.security_escrow_pubkey,
+			params.security_deposit,
+			params.rent_amount,
+			params.duration,
+			params.start_month,
+			params.start_year,
+		);
+		rent_data.annual_increase_bps = params.annual_increase_bps;
+		rent_data.refund_delay_days = params.refund_delay_days;
+		rent_data.payment_mint = params.payment_mint;
+		rent_data.first_month_proration_bps = params.first_month_proration_bps;
+		rent_data.late_fee = params.late_fee;
+		rent_data.max_occupants = params.max_occupants;
+		rent_data.platform_fee_bps = params.platform_fee_bps;
+		rent_data.platform_wallet = params.platform_wallet;
+		rent_data.arbiter_pubkey = params.arbiter_pubkey;
+		rent_data.allow_self_rent = params.allow_self_rent;
+		rent_data.payment_window_days = params.payment_window_days;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Rent Agreement account initialized successfully: {:?}", rent_data);
+
+		Ok(())
+	}
+
+
+	/// Initialize the Contract Account and pay the initial security_deposit amount (tenant -> escrow) in one go.
+	/// Reuses the initialization & deposit handlers so that both paths are validated identically.
+	fn initialize_and_deposit(accounts: &[AccountInfo], program_id: &Pubkey, params: &InitParams) -> ProgramResult {
+
+		if accounts.len() < 7 {
+			msg!("[TrustedProperties] ERROR: Expected 7 accounts to initialize the agreement & deposit the security, found {}", accounts.len());
+			return Err(ProgramError::NotEnoughAccountKeys);
+		}
+
+		if *accounts[3].key != params.tenant_pubkey {
+			msg!("[TrustedProperties] ERROR: Tenant account does not match the tenant's public-key (tenant_pubkey) of the agreement");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// Accounts: rent agreement, owner, sysvar rent, program config
+		let init_accounts = [
+			accounts[0].clone(),
+			accounts[1].clone(),
+			accounts[2].clone(),
+			accounts[6].clone(),
+		];
+		Self::initialize_rent_contract(&init_accounts, program_id, params)?;
+
+		// Accounts: rent agreement, tenant, escrow, system program
+		let deposit_accounts = [
+			accounts[0].clone(),
+			accounts[3].clone(),
+			accounts[4].clone(),
+			accounts[5].clone(),
+		];
+		Self::deposit_security(&deposit_accounts, program_id, params.security_deposit)
+	}
+
+
+	/// Initialize several Contract Accounts of the same owner (eg: the units of a multi-unit property) in one go.
+	/// Reuses the initialization handler, so that each agreement is validated independently & identically.
+	fn initialize_batch(accounts: &[AccountInfo], program_id: &Pubkey, agreements: &[InitParams]) -> ProgramResult {
+
+		if accounts.len() < 2 + agreements.len() {
+			msg!("[TrustedProperties] ERROR: Expected {} accounts to initialize a batch of {} agreements, found {}", 2 + agreements.len(), agreements.len(), accounts.len());
+			return Err(ProgramError::NotEnoughAccountKeys);
+		}
+
+		for (i, params) in agreements.iter().enumerate() {
+			msg!("[TrustedProperties] Initializing agreement {} of {} in the batch", i + 1, agreements.len());
+
+			// Accounts: rent agreement, owner, program config
+			let init_accounts = [
+				accounts[2 + i].clone(),
+				accounts[0].clone(),
+				accounts[1].clone(),
+			];
+			Self::initialize_rent_contract(&init_accounts, program_id, params)?;
+		}
+
+		msg!("[TrustedProperties] Batch of {} agreements initialized successfully", agreements.len());
+
+		Ok(())
+	}
+
+
+	/// Pay the rent (tenant -> owner)
+	fn pay_rent(accounts: &[AccountInfo], program_id: &Pubkey, rent_amount: u64, op_nonce: u64) -> ProgramResult {
+
+		let PayRentAccounts {
+			agreement: rent_agreement_account,
+			tenant: tenant_account,
+			owner: owner_account,
+			system_program: system_program_account,
+			platform_wallet: platform_wallet_account,
+		} = PayRentAccounts::parse(accounts, program_id)?;
+
+		if rent_amount == 0 {
+			msg!("[TrustedProperties] ERROR: Rent amount must not be zero");
+			return Err(TrustedPropertiesError::IncorrectPaymentAmount.into());
+		}
+
+		// Nonces start at 1: 0 is the `last_op_nonce` of an agreement without any payment, & would be taken as its replay
+		if op_nonce == 0 {
+			msg!("[TrustedProperties] ERROR: Payment nonce must not be zero");
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		if tenant_account.lamports() < rent_amount {
+			return Err(ProgramError::InsufficientFunds);
+		}
+
+		// A tenant account holding data must stay rent exempt after paying (a plain wallet has no such floor)
+		if tenant_account.data_len() > 0 {
+			let tenant_minimum = Rent::get()?.minimum_balance(tenant_account.data_len());
+			if tenant_account.lamports() - rent_amount < tenant_minimum {
+				msg!("[TrustedProperties] ERROR: Rent payment would leave the tenant account below its rent-exempt minimum. Balance: {}, payment: {}, minimum: {}", tenant_account.lamports(), rent_amount, tenant_minimum);
+				return Err(ProgramError::InsufficientFunds);
+			}
+		}
+
+		Self::with_agreement_mut(rent_agreement_account, |rent_data| {
+
+			// Make sure we pay the same account used during the agreement initialization
+			if rent_data.owner_pubkey != *owner_account.key {
+				msg!("[TrustedProperties] ERROR: Owner's public-key (owner_pubkey) does not match the one used during agreement initialization");
+				return Err(ProgramError::InvalidAccountData);
+			}
+
+			// A duplicate submission of the last payment is already applied
+			if op_nonce == rent_data.last_op_nonce {
+				msg!("[TrustedProperties] Payment with nonce {} already applied. Skipping.", op_nonce);
+				return Ok(());
+			}
+
+			if op_nonce < rent_data.last_op_nonce {
+				msg!("[TrustedProperties] ERROR: Stale payment nonce {} (last applied: {})", op_nonce, rent_data.last_op_nonce);
+				return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+			}
+
+			require_financially_active(rent_data)?;
+
+			if rent_data.is_token_denominated() {
+				msg!("[TrustedProperties] ERROR: Rent must be paid in tokens of the mint: {}", rent_data.payment_mint);
+				return Err(TrustedPropertiesError::InvalidInstruction.into());
+			}
+
+			if rent_data.autopay_remaining > 0 {
+				msg!("[TrustedProperties] ERROR: Rent is being paid by auto-pay ({} payments remaining)", rent_data.autopay_remaining);
+				return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+			}
+
+			// TODO: Allow advance payment (transfer amount more than the monthly rent amount). This can go into the escrow account as advance deposit.
+			// The rent due may have been escalated after each full year of the agreement, and includes the late fee when overdue
+			let payment_number = rent_data.payments_made.saturating_add(1);
+			let rent_due = amount_due_now(rent_data, payment_number)?;
+			require_exact_amount(rent_amount, rent_due)?;
+
+			// The payment is accepted: a new state change of the agreement
+			rent_data.next_event_seq()?;
+
+			// Transfer to self? Only counts (without any transfer) when opted-in at the initialization, eg: an owner-occupier.
+			// Checked only after the amount & status are validated.
+			if tenant_account.key == owner_account.key {
+				if !rent_data.allow_self_rent {
+					msg!("[TrustedProperties] ERROR: Tenant & owner are the same account, but self-rent is not allowed by the agreement");
+					return Err(ProgramError::InvalidAccountData);
+				}
+
+				msg!("[TrustedProperties] Tenant & owner are the same account. Payment #{} recorded without any transfer.", payment_number);
+				Self::record_rent_payment(rent_data, payment_number)?;
+				warn_if_refund_uncovered(accounts, rent_agreement_account, rent_data)?;
+				rent_data.last_op_nonce = op_nonce;
+				emit_rent_paid(rent_agreement_account, rent_data, payment_number, 0);
+				return Ok(());
+			}
+
+			// The platform's share of the payment (if any) goes to the platform wallet, the rest to the owner
+			let (platform_fee, owner_share) = platform_fee_split(rent_data, payment_number, rent_amount)?;
+			if platform_fee > 0 {
+				let platform_wallet_account = platform_wallet_for(platform_wallet_account, rent_data)?;
+				transfer_with_balance_log(tenant_account, platform_wallet_account, system_program_account, platform_fee, "Platform fee")?;
+			}
+
+			// Transfer the rest of the rent-amount (lamports) from tenant's account to the owner's account
+			transfer_with_balance_log(tenant_account, owner_account, system_program_account, owner_share, "Rent payment")?;
+
+			Self::record_rent_payment(rent_data, payment_number)?;
+			warn_if_refund_uncovered(accounts, rent_agreement_account, rent_data)?;
+			rent_data.last_op_nonce = op_nonce;
+			emit_rent_paid(rent_agreement_account, rent_data, payment_number, rent_amount);
+
+			Ok(())
+		})
+	}
+
+
+	/// Load the agreement state (borrowing the account data once), run `update` on it, then store it back once.
+	/// `update` calls `next_event_seq` when it changes the state: otherwise (eg: an ignored replay) nothing is stored.
+	/// The state is only stored if `update` succeeds, after checking the rent exemption & the state invariants.
+	fn with_agreement_mut<F>(rent_agreement_account: &AccountInfo, update: F) -> ProgramResult
+	where
+		F: FnOnce(&mut RentAgreementAccount) -> ProgramResult,
+	{
+		check_rent_exempt(rent_agreement_account)?;
+
+		let mut data = rent_agreement_account.try_borrow_mut_data()?;
+		let mut rent_data = agreement_from_data(&data)?;
+
+		let event_seq = rent_data.event_seq;
+		update(&mut rent_data)?;
+		if rent_data.event_seq == event_seq {
+			return Ok(());
+		}
+
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut data)?;
+
+		Ok(())
+	}
+
+
+	/// Refund the remaining security deposit (escrow -> tenant) after the agreement is complete & the refund delay has passed
+	fn refund_security_deposit(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let RefundSecurityDepositAccounts {
+			agreement: rent_agreement_account,
+			owner: owner_account,
+			tenant: tenant_account,
+			escrow: escrow_account,
+		} = RefundSecurityDepositAccounts::parse(accounts, program_id)?;
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		if rent_data.owner_pubkey != *owner_account.key || rent_data.tenant_pubkey != *tenant_account.key {
+			msg!("[TrustedProperties] ERROR: Owner & Tenant public-keys do not match the ones used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if !rent_data.is_completed() {
+			msg!("[TrustedProperties] ERROR: Agreement must be completed to refund the security deposit");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		// Without a refund delay the refund is never locked: the clock is only required to check a delay
+		if rent_data.refund_delay_days > 0 {
+			let now = clock_now()?;
+			let refund_unlock_ts = rent_data.refund_unlock_ts()?;
+			if now < refund_unlock_ts {
+				msg!("[TrustedProperties] ERROR: Security deposit refund is locked until {} (current time: {})", refund_unlock_ts, now);
+				return Err(TrustedPropertiesError::RefundLocked.into());
+			}
+		}
+
+		if rent_data.is_condition_report_pending() {
+			msg!("[TrustedProperties] ERROR: Move-out condition report must be recorded before the security deposit refund");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+		let refund = escrow.available_balance();
+
+		transfer_from_program_account(escrow_account, tenant_account, refund)?;
+
+		escrow.debit(refund)?;
+		escrow.status = AgreementStatus::Completed as u8;
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Security deposit of {} lamports refunded to the tenant", refund);
+
+		Ok(())
+	}
+
+
+	/// Record a monthly rent payment: track late payments, count the payment & complete the agreement after the last one.
+	/// Every payment path records its payments here, so that the payment window is enforced for all of them.
+	fn record_rent_payment(rent_data: &mut RentAgreementAccount, payment_number: u8) -> ProgramResult {
+
+		// Payments are only accepted from the payment window before the due date (if any), and
+		// late payments are tracked for the tenant's reputation. Both skipped in clock-optional mode.
+		if let Some(now) = clock_now_optional() {
+			if rent_data.is_payment_too_early(payment_number, now) {
+				msg!("[TrustedProperties] ERROR: Payment #{} is due at {}: payments are accepted at most {} days before", payment_number, rent_data.due_ts_for_payment(payment_number), rent_data.payment_window_days);
+				return Err(TrustedPropertiesError::PaymentTooEarly.into());
+			}
+
+			if rent_data.is_payment_late(payment_number, now) {
+				msg!("[TrustedProperties] Late payment. Payment #{} was due at {}", payment_number, rent_data.due_ts_for_payment(payment_number));
+				rent_data.late_payment_count = rent_data.late_payment_count.saturating_add(1);
+			}
+		}
+
+		// Decrement the number of payment
+		rent_data.payments_made = rent_data.payments_made
+			.checked_add(1)
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		rent_data.remaining_payments = rent_data.remaining_payments
+			.checked_sub(1)
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		if rent_data.remaining_payments == 0 {
+			rent_data.status = AgreementStatus::Completed as u8;
+			rent_data.completed_ts = completion_ts(rent_data);
+		}
+
+		Ok(())
+	}
+
+
+	/// Pay the initial security_deposit amount (tenant -> escrow)
+	/// TODO: Revert the security_deposit to the tenant after agreement period
+	/// TODO: 	or, make the last n payments from security_deposit escrow account.
+	/// TODO:	Also, use this for penalty if the tenant terminates the agreement early.
+	/// TODO: Merge with pay_rent function to create a common generic function.
+	fn deposit_security(accounts: &[AccountInfo], program_id: &Pubkey, security_deposit_amount: u64) -> ProgramResult {
+
+		let DepositSecurityAccounts {
+			agreement: rent_agreement_account,
+			tenant: tenant_account,
+			escrow: escrow_account,
+			system_program: system_program_account,
+		} = DepositSecurityAccounts::parse(accounts, program_id)?;
+
+		if tenant_account.lamports() < security_deposit_amount {
+			return Err(ProgramError::InsufficientFunds);
+		}
+
+		// Transfer to self? Do nothing.
+		if tenant_account.key == escrow_account.key {
+			return Ok(());
+		}
+
+		// Initialize the Rent Agreement Account with the initial data
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		// Make sure we pay the same account used during the agreement initialization
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.is_disputed() {
+			msg!("[TrustedProperties] ERROR: Rent agreement is on hold due to a dispute");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		if !rent_data.is_security_deposit_pending() {
+			msg!("[TrustedProperties] ERROR: Security already deposited");
+			return Err(TrustedPropertiesError::SecurityAlreadyDeposited.into());
+		}
+
+		// TODO: Allow advance payment (transfer amount more than the monthly rent amount)
+		require_exact_amount(security_deposit_amount, rent_data.security_deposit)?;
+
+		if escrow_account.data_len() != SecurityEscrowAccount::LEN {
+			msg!("[TrustedProperties] ERROR: Security escrow account data size ({}) must be {} bytes", escrow_account.data_len(), SecurityEscrowAccount::LEN);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// The escrow must be fresh: not holding the deposit of another agreement naming the same escrow
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+
+		// Transfer the security deposit amount (lamports) from tenant's account to the escrow account
+		transfer_with_balance_log(tenant_account, escrow_account, system_program_account, security_deposit_amount, "Security deposit")?;
+
+		// Record the deposit in the escrow account state
+		escrow.status = AgreementStatus::Active as u8;
+		escrow.agreement_pubkey = *rent_agreement_account.key;
+		escrow.owner_pubkey = rent_data.owner_pubkey;
+		escrow.tenant_pubkey = rent_data.tenant_pubkey;
+		escrow.security_deposit = Lamports(rent_data.security_deposit);
+		escrow.credit(security_deposit_amount)?;
+		escrow.total_deposited = escrow.total_deposited
+			.checked_add(Lamports(security_deposit_amount))
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		// Deposit payment done. Therefore, mark the agreement account as active.
+		rent_data.status = AgreementStatus::Active as u8;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Top up the security deposit held in escrow (tenant -> escrow)
+	fn top_up_escrow(accounts: &[AccountInfo], program_id: &Pubkey, amount: u64) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let escrow_account: &AccountInfo = next_account(accounts_iter, "escrow account")?;
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(escrow_account, "security escrow account")?;
+
+		if tenant_account.lamports() < amount {
+			return Err(ProgramError::InsufficientFunds);
+		}
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_financially_active(&rent_data)?;
+
+		// Make sure we pay the same account used during the agreement initialization
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+		escrow.credit(amount)?;
+		escrow.total_deposited = escrow.total_deposited
+			.checked_add(Lamports(amount))
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+
+		// Transfer the top-up amount (lamports) from tenant's account to the escrow account
+		transfer_with_balance_log(tenant_account, escrow_account, system_program_account, amount, "Escrow top-up")?;
+
+		msg!("[TrustedProperties] Escrow top-up completed. Remaining deposit in escrow: {}", escrow.remaining_deposit);
+
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Terminate the contract early
+	fn terminate_early(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let party_account = next_account(accounts_iter, "owner or tenant signer")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		// Either party can terminate: the owner, or else the tenant
+		let party_pubkey = if *party_account.key == rent_data.owner_pubkey { rent_data.owner_pubkey } else { rent_data.tenant_pubkey };
+		require_signing_party(party_account, &party_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if rent_data.is_disputed() {
+			msg!("[TrustedProperties] ERROR: Rent agreement is on hold due to a dispute");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		rent_data.remaining_payments = 0;
+		rent_data.status = AgreementStatus::Terminated as u8;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Extend the contract duration.
+	fn extend_contract_duration_request(accounts: &[AccountInfo], program_id: &Pubkey, extension_duration: u8) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+
+		// 0 marks "no pending request"
+		if extension_duration == 0 {
+			msg!("[TrustedProperties] ERROR: Extension duration must be at least 1 month");
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		require_financially_active(&rent_data)?;
+
+		// No remaining term to extend: the agreement is concluding (an extension can still be requested in its final month)
+		if rent_data.remaining_payments == 0 {
+			msg!("[TrustedProperties] ERROR: No remaining payments: the agreement is concluding & cannot be extended");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		// Update the Agreement Duration Extension request
+		rent_data.duration_extension_request = extension_duration;
+		rent_data.extension_request_ts = clock_now()?;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Confirm the extension of contract duration (by Owner).
+	/// With `partial`, the owner may approve fewer months than requested (a counter-offer), otherwise exactly the requested ones.
+	fn extend_contract_duration_confirm(accounts: &[AccountInfo], program_id: &Pubkey, extension_duration: u8, partial: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+
+		require_financially_active(&rent_data)?;
+
+		// A zero request is the "no pending request" sentinel: never confirm it (even against a zero extension)
+		if rent_data.duration_extension_request == 0 {
+			msg!("[TrustedProperties] ERROR: No pending duration extension request to confirm");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		if partial {
+			if extension_duration == 0 || extension_duration > rent_data.duration_extension_request {
+				msg!("[TrustedProperties] ERROR: Approved duration ({}) must be between 1 and the requested one ({}).", extension_duration, rent_data.duration_extension_request);
+				return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+			}
+			msg!("[TrustedProperties] Extension negotiated: requested {} months, approved {} months", rent_data.duration_extension_request, extension_duration);
+		} else if rent_data.duration_extension_request != extension_duration {
+			msg!("[TrustedProperties] ERROR: Extension duration ({}) does not match the requested one ({}).", extension_duration, rent_data.duration_extension_request);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let now = clock_now()?;
+		if rent_data.is_extension_request_stale(now) {
+			msg!("[TrustedProperties] ERROR: Extension request (made at {}) is older than {} days", rent_data.extension_request_ts, EXTENSION_REQUEST_WINDOW_DAYS);
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		// Update the Agreement Duration Extension
+		let old_duration = rent_data.duration;
+		rent_data.duration = rent_data.duration
+			.checked_add(extension_duration)
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		rent_data.remaining_payments = rent_data.remaining_payments
+			.checked_add(extension_duration)
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		rent_data.duration_extension_request = 0;
+		rent_data.extension_request_ts = 0;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		let (end_month, end_year) = rent_data.end_month_year();
+		msg!("[TrustedProperties] EXTENDED: old_duration={} added_months={} new_duration={} end_month={} end_year={}", old_duration, extension_duration, rent_data.duration, end_month, end_year);
+
+		Ok(())
+	}
+
+
+	/// Raise a dispute (by Owner or Tenant) & put the agreement on hold.
+	fn raise_dispute(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let party_account = next_account(accounts_iter, "owner or tenant signer")?;
+
+		if !party_account.is_signer {
+			msg!("[TrustedProperties] Owner or Tenant must sign the dispute");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		if rent_data.owner_pubkey != *party_account.key && rent_data.tenant_pubkey != *party_account.key {
+			msg!("[TrustedProperties] ERROR: Only the owner or the tenant of the agreement can raise a dispute");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.status != AgreementStatus::Active as u8 {
+			msg!("[TrustedProperties] ERROR: Agreement must be active to raise a dispute");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		rent_data.status = AgreementStatus::Disputed as u8;
+		rent_data.disputed_by = *party_account.key;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Dispute raised by: {}", party_account.key);
+
+		Ok(())
+	}
+
+
+	/// Resolve a dispute (by both Owner and Tenant) & resume the agreement.
+	fn resolve_dispute(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if !rent_data.is_disputed() {
+			msg!("[TrustedProperties] ERROR: Agreement is not disputed");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		rent_data.status = AgreementStatus::Active as u8;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Dispute resolved. Agreement is active again.");
+
+		Ok(())
+	}
+
+
+	/// Verify that the escrow account balance covers the remaining security deposit tracked in its state.
+	fn verify_escrow_solvency(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let solana_rent = &Rent::from_account_info(next_account(accounts_iter, "rent sysvar account")?)?;
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		let escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+
+		// Lamports above the rent-exempt minimum are the ones actually available for the deposit
+		let rent_exempt_minimum = escrow_rent_exempt_minimum(solana_rent);
+		let available_balance = escrow_account.lamports().saturating_sub(rent_exempt_minimum);
+
+		// The escrow also holds the rent prepaid for auto-pay, on top of the deposit
+		let tracked_balance = u64::from(escrow.remaining_deposit).saturating_add(rent_data.prepaid_rent);
+
+		if available_balance < tracked_balance {
+			msg!("[TrustedProperties] ERROR: Escrow insolvent. Tracked deposit: {}, prepaid rent: {}, available balance: {} (balance: {}, rent-exempt minimum: {}), shortfall: {}", escrow.remaining_deposit, rent_data.prepaid_rent, available_balance, escrow_account.lamports(), rent_exempt_minimum, tracked_balance - available_balance);
+			return Err(TrustedPropertiesError::EscrowInsolvent.into());
+		}
+
+		msg!("[TrustedProperties] Escrow solvent. Tracked deposit: {}, prepaid rent: {}, available balance: {}", escrow.remaining_deposit, rent_data.prepaid_rent, available_balance);
+
+		Ok(())
+	}
+
+
+	/// Get the tenant's reputation summary for the agreement (via return data)
+	fn get_tenant_reputation(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		let reputation = TenantReputation {
+			late_payment_count: rent_data.late_payment_count,
+			payments_made: rent_data.payments_made,
+			terminated_early: rent_data.is_terminated(),
+			disputed: rent_data.disputed_by != Pubkey::default(),
+		};
+
+		msg!("[TrustedProperties] Tenant reputation: {:?}", reputation);
+
+		set_return_data(&reputation.try_to_vec()?);
+
+		Ok(())
+	}
+
+
+	/// Terminate the contract early (by Tenant), charging a prorated rent for the current month from the security deposit.
+	fn terminate_with_proration(accounts: &[AccountInfo], program_id: &Pubkey, days_occupied: u8) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let owner_account = next_account(accounts_iter, "owner account")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(escrow_account, "security escrow account")?;
+
+		if days_occupied > 30 {
+			msg!("[TrustedProperties] ERROR: Days occupied ({}) must not exceed 30", days_occupied);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+		if rent_data.owner_pubkey != *owner_account.key {
+			msg!("[TrustedProperties] ERROR: Owner's public-key (owner_pubkey) does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.status != AgreementStatus::Active as u8 {
+			msg!("[TrustedProperties] ERROR: Agreement must be active to terminate with proration");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+
+		// Prorated rent for the days occupied in the current month, charged from the security deposit.
+		// A breach of the occupancy clause forfeits the proration: the full month is charged.
+		let settlement = compute_settlement(&rent_data, &escrow, days_occupied)?;
+
+		msg!("[TrustedProperties] Terminating with proration: {} days occupied, prorated rent: {}, charged from deposit: {}, refunded to tenant: {} (prepaid rent: {}), owed to owner: {}", days_occupied, settlement.prorated_rent, settlement.deposit_to_owner, settlement.deposit_to_tenant, settlement.prepaid_to_tenant, settlement.shortfall);
+
+		transfer_from_program_account(escrow_account, owner_account, settlement.deposit_to_owner)?;
+		transfer_from_program_account(escrow_account, tenant_account, settlement.to_tenant())?;
+
+		// Rent prepaid for auto-pay is no longer needed
+		rent_data.prepaid_rent = 0;
+		rent_data.autopay_remaining = 0;
+
+		escrow.debit(settlement.deposit_to_owner)?;
+		escrow.debit(settlement.deposit_to_tenant)?;
+		escrow.penalties_applied = escrow.penalties_applied.saturating_add(Lamports(settlement.deposit_to_owner));
+		escrow.status = AgreementStatus::Terminated as u8;
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		rent_data.remaining_payments = 0;
+		rent_data.owed_to_owner = rent_data.owed_to_owner.saturating_add(settlement.shortfall);
+		rent_data.status = AgreementStatus::Terminated as u8;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Get the financial ledger of the agreement (via return data)
+	fn get_ledger(accounts: &[AccountInfo], program_id: &Pubkey, require_party: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if require_party {
+			check_party_signer(next_account(accounts_iter, "owner or tenant signer")?, &rent_data)?;
+		}
+
+		let escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+
+		let ledger = Ledger {
+			total_paid_lamports: rent_data.total_rent_paid()?,
+			total_remaining_lamports: if rent_data.is_terminated() { 0 } else { rent_data.total_rent_remaining()? },
+			deposit_held_lamports: escrow.remaining_deposit.into(),
+			penalties_applied_lamports: escrow.penalties_applied.into(),
+			owed_to_owner_lamports: rent_data.owed_to_owner,
+		};
+
+		msg!("[TrustedProperties] Ledger: {:?}", ledger);
+
+		set_return_data(&ledger.try_to_vec()?);
+
+		Ok(())
+	}
+
+
+	/// Migrate the security deposit to a new escrow PDA (by both Owner and Tenant)
+	fn migrate_escrow(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let old_escrow_account = next_account(accounts_iter, "current escrow account")?;
+		let new_escrow_account = next_account(accounts_iter, "new escrow account")?;
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		if old_escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(old_escrow_account, "old security escrow account")?;
+
+		let (escrow_pda, escrow_bump) = Pubkey::find_program_address(&[ESCROW_SEED, rent_agreement_account.key.as_ref()], program_id);
+		if escrow_pda != *new_escrow_account.key {
+			msg!("[TrustedProperties] ERROR: New escrow account does not match the derived escrow PDA: {}", escrow_pda);
+			return Err(ProgramError::InvalidSeeds);
+		}
+		check_writable_state(new_escrow_account, "new security escrow account")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if rent_data.security_escrow_pubkey != *old_escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.status != AgreementStatus::Active as u8 {
+			msg!("[TrustedProperties] ERROR: Agreement must be active to migrate the escrow");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		// Only the security deposit is migrated: the rent prepaid for auto-pay must not be left behind in the old escrow
+		if rent_data.autopay_remaining > 0 || rent_data.prepaid_rent > 0 {
+			msg!("[TrustedProperties] ERROR: Escrow cannot be migrated while auto-pay payments are pending or prepaid rent is held");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let mut old_escrow = load_escrow(old_escrow_account, rent_agreement_account, &rent_data)?;
+
+		// Create the new escrow PDA (owned by this program) with the same layout as the current escrow
+		let space = old_escrow_account.data_len();
+		let lamports = Rent::get()?.minimum_balance(space);
+		check_system_program(system_program_account)?;
+		invoke_signed(
+			&system_instruction::create_account(owner_account.key, new_escrow_account.key, lamports, space as u64, program_id),
+			&[
+				owner_account.clone(),
+				new_escrow_account.clone(),
+				system_program_account.clone(),
+			],
+			&[&[ESCROW_SEED, rent_agreement_account.key.as_ref(), &[escrow_bump]]],
+		)?;
+
+		// Move the full remaining deposit to the new escrow
+		transfer_from_program_account(old_escrow_account, new_escrow_account, old_escrow.remaining_deposit.into())?;
+
+		let new_escrow = SecurityEscrowAccount {
+			status: old_escrow.status,
+			agreement_pubkey: old_escrow.agreement_pubkey,
+			owner_pubkey: old_escrow.owner_pubkey,
+			tenant_pubkey: old_escrow.tenant_pubkey,
+			security_deposit: old_escrow.security_deposit,
+			remaining_deposit: old_escrow.remaining_deposit,
+			total_deposited: old_escrow.total_deposited,
+			penalties_applied: old_escrow.penalties_applied,
+			closed: false,
+		};
+		pack_into(&new_escrow, &mut new_escrow_account.data.borrow_mut())?;
+
+		old_escrow.debit(new_escrow.remaining_deposit.into())?;
+		old_escrow.closed = true;
+		pack_into(&old_escrow, &mut old_escrow_account.data.borrow_mut())?;
+
+		rent_data.security_escrow_pubkey = escrow_pda;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Escrow migrated from {} to {}. Deposit moved: {}", old_escrow_account.key, escrow_pda, new_escrow.remaining_deposit);
+
+		Ok(())
+	}
+
+
+	/// Transfer tokens (via the SPL Token program) from the signing authority's token account
+	fn transfer_tokens<'a>(
+		from: &AccountInfo<'a>,
+		to: &AccountInfo<'a>,
+		authority: &AccountInfo<'a>,
+		token_program: &AccountInfo<'a>,
+		amount: u64,
+	) -> ProgramResult {
+		invoke(
+			&spl_token::instruction::transfer(
+				token_program.key,
+				from.key,
+				to.key,
+				authority.key,
+				&[],
+				amount,
+			)?,
+			&[
+				from.clone(),
+				to.clone(),
+				authority.clone(),
+				token_program.clone(),
+			],
+		)
+	}
+
+
+	/// Pay the rent in SPL tokens (tenant -> owner)
+	fn pay_rent_token(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let tenant_token_account = next_account(accounts_iter, "tenant token account")?;
+		let owner_token_account = next_account(accounts_iter, "owner token account")?;
+		let token_program_account = next_account(accounts_iter, "token program account")?;
+
+		if !tenant_account.is_signer {
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		if *token_program_account.key != spl_token::id() {
+			msg!("[TrustedProperties] ERROR: Token program account is not the SPL Token program");
+			return Err(ProgramError::IncorrectProgramId);
+		}
+
+		if *tenant_token_account.owner != spl_token::id() || *owner_token_account.owner != spl_token::id() {
+			msg!("[TrustedProperties] ERROR: Token accounts must be owned by the SPL Token program");
+			return Err(ProgramError::IncorrectProgramId);
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_financially_active(&rent_data)?;
+
+		if !rent_data.is_token_denominated() {
+			msg!("[TrustedProperties] ERROR: Rent of this agreement must be paid in SOL");
+			return Err(TrustedPropertiesError::InvalidInstruction.into());
+		}
+
+		let tenant_token = TokenAccount::unpack(&tenant_token_account.data.borrow())?;
+		let owner_token = TokenAccount::unpack(&owner_token_account.data.borrow())?;
+
+		// Pin the payment to the accepted token
+		if tenant_token.mint != rent_data.payment_mint || owner_token.mint != rent_data.payment_mint {
+			msg!("[TrustedProperties] ERROR: Token accounts must be of the agreement's payment mint: {}", rent_data.payment_mint);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// Make sure we pay the same owner used during the agreement initialization
+		if owner_token.owner != rent_data.owner_pubkey {
+			msg!("[TrustedProperties] ERROR: Owner's token account does not belong to the owner of the agreement");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if tenant_token.owner != *tenant_account.key {
+			msg!("[TrustedProperties] ERROR: Tenant's token account does not belong to the signing tenant");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		let payment_number = rent_data.payments_made.saturating_add(1);
+		let rent_due = amount_due_now(&rent_data, payment_number)?;
+		if tenant_token.amount < rent_due {
+			return Err(ProgramError::InsufficientFunds);
+		}
+
+		// The platform's share of the payment (if any) goes to the platform wallet's token account, the rest to the owner
+		let (platform_fee, owner_share) = platform_fee_split(&rent_data, payment_number, rent_due)?;
+		if platform_fee > 0 {
+			let platform_token_account = next_account(accounts_iter, "platform token account")?;
+			if *platform_token_account.owner != spl_token::id() {
+				msg!("[TrustedProperties] ERROR: Token accounts must be owned by the SPL Token program");
+				return Err(ProgramError::IncorrectProgramId);
+			}
+
+			let platform_token = TokenAccount::unpack(&platform_token_account.data.borrow())?;
+			if platform_token.mint != rent_data.payment_mint || platform_token.owner != rent_data.platform_wallet {
+				msg!("[TrustedProperties] ERROR: Platform token account must be of the payment mint & belong to the platform wallet: {}", rent_data.platform_wallet);
+				return Err(ProgramError::InvalidAccountData);
+			}
+
+			Self::transfer_tokens(tenant_token_account, platform_token_account, tenant_account, token_program_account, platform_fee)?;
+		}
+
+		// Transfer the rest of the rent-amount (tokens) from tenant's token account to the owner's token account
+		Self::transfer_tokens(tenant_token_account, owner_token_account, tenant_account, token_program_account, owner_share)?;
+
+		msg!("[TrustedProperties] Rent payment of {} tokens (mint: {}) completed", rent_due, rent_data.payment_mint);
+
+		Self::record_rent_payment(&mut rent_data, payment_number)?;
+		warn_if_refund_uncovered(accounts, rent_agreement_account, &rent_data)?;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+		emit_rent_paid(rent_agreement_account, &rent_data, payment_number, rent_due);
+
+		Ok(())
+	}
+
+
+	/// Pause (or resume) the initialization of new agreements (by the program authority)
+	fn set_paused(accounts: &[AccountInfo], program_id: &Pubkey, paused: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let config_account = next_account(accounts_iter, "program config account")?;
+		let authority_account = next_account(accounts_iter, "program authority signer")?;
+		let program_data_account = next_account(accounts_iter, "program data account")?;
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		if !authority_account.is_signer {
+			msg!("[TrustedProperties] ERROR: Program authority must sign to pause or resume the program");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		let (config_pda, config_bump) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+		if config_pda != *config_account.key {
+			msg!("[TrustedProperties] ERROR: Program config account does not match the derived config PDA: {}", config_pda);
+			return Err(ProgramError::InvalidSeeds);
+		}
+
+		let mut config = if config_account.data_is_empty() {
+			// First use: only the program's upgrade authority may create the config (& becomes its authority)
+			if program_upgrade_authority(program_data_account, program_id)? != Some(*authority_account.key) {
+				msg!("[TrustedProperties] ERROR: Only the program's upgrade authority can create the program config");
+				return Err(ProgramError::InvalidAccountData);
+			}
+
+			let config = ProgramConfig { authority: *authority_account.key, ..ProgramConfig::default() };
+			let space = ProgramConfig::LEN;
+			let lamports = Rent::get()?.minimum_balance(space);
+			check_system_program(system_program_account)?;
+			invoke_signed(
+				&system_instruction::create_account(authority_account.key, config_account.key, lamports, space as u64, program_id),
+				&[
+					authority_account.clone(),
+					config_account.clone(),
+					system_program_account.clone(),
+				],
+				&[&[CONFIG_SEED, &[config_bump]]],
+			)?;
+			config
+		} else {
+			if config_account.owner != program_id {
+				msg!("[TrustedProperties] ERROR: Program config account must be owned by this program");
+				return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+			}
+			check_writable_state(config_account, "program config account")?;
+
+			let config_data = ProgramConfig::try_from_slice_versioned(&config_account.data.borrow());
+			if config_data.is_err() {
+				msg!("[TrustedProperties] ERROR: Program config account data size incorrect: {}", config_account.try_data_len()?);
+				return Err(ProgramError::InvalidAccountData);
+			}
+
+			let config = config_data.unwrap();
+			if config.authority != *authority_account.key {
+				msg!("[TrustedProperties] ERROR: Only the program authority can pause or resume the program");
+				return Err(ProgramError::InvalidAccountData);
+			}
+			config
+		};
+
+		config.paused = paused;
+		pack_config(&config, &mut config_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Program {}", if paused { "paused" } else { "resumed" });
+
+		Ok(())
+	}
+
+
+	/// Get the program-wide parameters in effect (the defaults until the config is created)
+	fn get_config(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let config_account = next_account(accounts_iter, "program config account")?;
+
+		let (config_pda, _) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+		if config_pda != *config_account.key {
+			msg!("[TrustedProperties] ERROR: Program config account does not match the derived config PDA: {}", config_pda);
+			return Err(ProgramError::InvalidSeeds);
+		}
+
+		let config = if config_account.data_is_empty() {
+			ProgramConfig::default()
+		} else {
+			if config_account.owner != program_id {
+				msg!("[TrustedProperties] ERROR: Program config account must be owned by this program");
+				return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+			}
+
+			let config_data = ProgramConfig::try_from_slice_versioned(&config_account.data.borrow());
+			if config_data.is_err() {
+				msg!("[TrustedProperties] ERROR: Program config account data size incorrect: {}", config_account.try_data_len()?);
+				return Err(ProgramError::InvalidAccountData);
+			}
+			config_data.unwrap()
+		};
+
+		msg!("[TrustedProperties] Config: {:?}", config);
+
+		set_return_data(&config.try_to_vec()?);
+
+		Ok(())
+	}
+
+
+	/// Update the program-wide parameters of new agreements (by the program authority)
+	fn set_config(accounts: &[AccountInfo], program_id: &Pubkey, max_platform_fee_bps: u16, max_duration: u8, min_deposit_months: u8) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let config_account = next_account(accounts_iter, "program config account")?;
+		let authority_account = next_account(accounts_iter, "program authority signer")?;
+
+		if !authority_account.is_signer {
+			msg!("[TrustedProperties] ERROR: Program authority must sign to update the program config");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		let (config_pda, _) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+		if config_pda != *config_account.key {
+			msg!("[TrustedProperties] ERROR: Program config account does not match the derived config PDA: {}", config_pda);
+			return Err(ProgramError::InvalidSeeds);
+		}
+
+		// The config is created by the first `SetPaused`, which verifies the program's upgrade authority
+		if config_account.data_is_empty() {
+			msg!("[TrustedProperties] ERROR: Program config not created yet (see SetPaused)");
+			return Err(ProgramError::UninitializedAccount);
+		}
+
+		if config_account.owner != program_id {
+			msg!("[TrustedProperties] ERROR: Program config account must be owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(config_account, "program config account")?;
+
+		if config_account.data_len() < ProgramConfig::LEN {
+			msg!("[TrustedProperties] ERROR: Program config account ({} bytes) predates the governable parameters ({} bytes)", config_account.data_len(), ProgramConfig::LEN);
+			return Err(ProgramError::AccountDataTooSmall);
+		}
+
+		let config_data = ProgramConfig::try_from_slice_versioned(&config_account.data.borrow());
+		if config_data.is_err() {
+			msg!("[TrustedProperties] ERROR: Program config account data size incorrect: {}", config_account.try_data_len()?);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		let mut config = config_data.unwrap();
+		if config.authority != *authority_account.key {
+			msg!("[TrustedProperties] ERROR: Only the program authority can update the program config");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if max_platform_fee_bps > MAX_PLATFORM_FEE_BPS {
+			msg!("[TrustedProperties] ERROR: Maximum platform fee ({} bps) must not exceed {} bps", max_platform_fee_bps, MAX_PLATFORM_FEE_BPS);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		if max_duration == 0 {
+			msg!("[TrustedProperties] ERROR: Maximum duration must be at least 1 month");
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		config.max_platform_fee_bps = max_platform_fee_bps;
+		config.max_duration = max_duration;
+		config.min_deposit_months = min_deposit_months;
+		pack_config(&config, &mut config_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Config updated: {:?}", config);
+
+		Ok(())
+	}
+
+
+	/// Settle the penalty shortfall not covered by the security deposit (tenant -> owner)
+	fn settle_shortfall(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let owner_account = next_account(accounts_iter, "owner account")?;
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_sealed(&rent_data)?;
+
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+		if rent_data.owner_pubkey != *owner_account.key {
+			msg!("[TrustedProperties] ERROR: Owner's public-key (owner_pubkey) does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.owed_to_owner == 0 {
+			msg!("[TrustedProperties] ERROR: Nothing is owed to the owner");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		transfer_with_balance_log(tenant_account, owner_account, system_program_account, rent_data.owed_to_owner, "Shortfall settlement")?;
+
+		rent_data.owed_to_owner = 0;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Record the hash of a move-in (by Tenant) or move-out (by Owner) condition report
+	fn record_condition_report(accounts: &[AccountInfo], program_id: &Pubkey, report_hash: [u8; 32], is_movein: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let party_account = next_account(accounts_iter, if is_movein { "tenant signer" } else { "owner signer" })?;
+		if !party_account.is_signer {
+			msg!("[TrustedProperties] {} must sign the condition report", if is_movein { "Tenant" } else { "Owner" });
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		if report_hash == [0; 32] {
+			msg!("[TrustedProperties] ERROR: Condition report hash must not be empty");
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_sealed(&rent_data)?;
+
+		// The move-in report is the tenant's, the move-out report is the owner's
+		let (expected_party, recorded_hash) = if is_movein {
+			(rent_data.tenant_pubkey, &mut rent_data.movein_report_hash)
+		} else {
+			(rent_data.owner_pubkey, &mut rent_data.moveout_report_hash)
+		};
+
+		if expected_party != *party_account.key {
+			msg!("[TrustedProperties] ERROR: The {} report must be signed by the {}", if is_movein { "move-in" } else { "move-out" }, if is_movein { "tenant" } else { "owner" });
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if *recorded_hash != [0; 32] {
+			msg!("[TrustedProperties] ERROR: The {} condition report is already recorded", if is_movein { "move-in" } else { "move-out" });
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		*recorded_hash = report_hash;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] {} condition report recorded", if is_movein { "Move-in" } else { "Move-out" });
+
+		Ok(())
+	}
+
+
+	/// Authorize a cranker to trigger the next rent payments, prepaying their rent into the escrow (by Tenant)
+	fn authorize_auto_pay(accounts: &[AccountInfo], program_id: &Pubkey, max_payments: u8) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let cranker_account = next_account(accounts_iter, "cranker account")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		require_financially_active(&rent_data)?;
+
+		if rent_data.is_token_denominated() {
+			msg!("[TrustedProperties] ERROR: Auto-pay is only available for rent paid in SOL");
+			return Err(TrustedPropertiesError::InvalidInstruction.into());
+		}
+
+		if rent_data.autopay_remaining > 0 {
+			msg!("[TrustedProperties] ERROR: Auto-pay already authorized ({} payments remaining)", rent_data.autopay_remaining);
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		if max_payments == 0 || max_payments > rent_data.remaining_payments {
+			msg!("[TrustedProperties] ERROR: Auto-pay payments ({}) must be between 1 and the remaining payments ({})", max_payments, rent_data.remaining_payments);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		// Prepay the (possibly escalated) rent of the authorized payments into the escrow
+		let first_payment = rent_data.payments_made.saturating_add(1);
+		let prepaid_rent = rent_data.total_rent_for_payments(first_payment, rent_data.payments_made.saturating_add(max_payments))?;
+		transfer_with_balance_log(tenant_account, escrow_account, system_program_account, prepaid_rent, "Auto-pay prepayment")?;
+
+		rent_data.autopay_remaining = max_payments;
+		rent_data.cranker_pubkey = *cranker_account.key;
+		rent_data.prepaid_rent = prepaid_rent;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Auto-pay authorized for {} payments (cranker: {})", max_payments, cranker_account.key);
+
+		Ok(())
+	}
+
+
+	/// Trigger the next auto-pay rent payment from the prepaid rent (escrow -> owner), by the authorized cranker
+	fn crank_auto_pay(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let cranker_account = next_account(accounts_iter, "cranker signer")?;
+		let owner_account = next_account(accounts_iter, "owner account")?;
+		let tenant_account = next_account(accounts_iter, "tenant account")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+		let platform_wallet_account = accounts_iter.next();
+
+		if !cranker_account.is_signer {
+			msg!("[TrustedProperties] Cranker must sign the auto-pay payment");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		if rent_data.owner_pubkey != *owner_account.key || rent_data.tenant_pubkey != *tenant_account.key {
+			msg!("[TrustedProperties] ERROR: Owner & Tenant public-keys do not match the ones used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		require_financially_active(&rent_data)?;
+
+		if rent_data.autopay_remaining == 0 || rent_data.cranker_pubkey != *cranker_account.key {
+			msg!("[TrustedProperties] ERROR: Cranker is not authorized to trigger rent payments for this agreement");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		let now = clock_now()?;
+		let payment_number = rent_data.payments_made.saturating_add(1);
+		if !rent_data.is_payment_due(payment_number, now) {
+			msg!("[TrustedProperties] ERROR: Payment #{} is not due until {} (current time: {})", payment_number, rent_data.due_ts_for_payment(payment_number), now);
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let rent_due = rent_data.rent_for_payment(payment_number)?;
+		if rent_due > rent_data.prepaid_rent {
+			msg!("[TrustedProperties] ERROR: Prepaid rent ({}) does not cover the rent due ({})", rent_data.prepaid_rent, rent_due);
+			return Err(TrustedPropertiesError::EscrowInsolvent.into());
+		}
+
+		// The platform's share of the payment (if any) goes to the platform wallet, the rest to the owner
+		let (platform_fee, owner_share) = platform_fee_split(&rent_data, payment_number, rent_due)?;
+		if platform_fee > 0 {
+			let platform_wallet_account = platform_wallet_for(platform_wallet_account, &rent_data)?;
+			transfer_from_program_account(escrow_account, platform_wallet_account, platform_fee)?;
+		}
+
+		transfer_from_program_account(escrow_account, owner_account, owner_share)?;
+		rent_data.prepaid_rent -= rent_due;
+		rent_data.autopay_remaining -= 1;
+
+		Self::record_rent_payment(&mut rent_data, payment_number)?;
+
+		// Nothing left to pay for: return the prepaid rent left over (if any) to the tenant
+		if rent_data.is_completed() && rent_data.prepaid_rent > 0 {
+			msg!("[TrustedProperties] Refunding unused prepaid rent to the tenant: {}", rent_data.prepaid_rent);
+			transfer_from_program_account(escrow_account, tenant_account, rent_data.prepaid_rent)?;
+			rent_data.prepaid_rent = 0;
+			rent_data.autopay_remaining = 0;
+		}
+
+		warn_if_refund_uncovered(accounts, rent_agreement_account, &rent_data)?;
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+		emit_rent_paid(rent_agreement_account, &rent_data, payment_number, rent_due);
+
+		msg!("[TrustedProperties] Auto-pay payment #{} of {} made ({} auto-pay payments remaining)", payment_number, rent_due, rent_data.autopay_remaining);
+
+		Ok(())
+	}
+
+
+	/// Set the co-owners of the property, who can receive a share of the rent (by Owner)
+	fn set_co_owners(accounts: &[AccountInfo], program_id: &Pubkey, co_owners: &[Pubkey]) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+
+		if co_owners.len() > MAX_CO_OWNERS || co_owners.contains(&Pubkey::default()) {
+			msg!("[TrustedProperties] ERROR: Up to {} co-owners (with valid public-keys) can be set", MAX_CO_OWNERS);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		rent_data.co_owners = [Pubkey::default(); MAX_CO_OWNERS];
+		rent_data.co_owners[..co_owners.len()].copy_from_slice(co_owners);
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Co-owners set: {:?}", co_owners);
+
+		Ok(())
+	}
+
+
+	/// Pay the rent split between the owner & co-owners by their share (tenant -> each payee)
+	fn pay_rent_split(accounts: &[AccountInfo], program_id: &Pubkey, splits: &[(Pubkey, u16)]) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		let total_bps: u32 = splits.iter().map(|(_, share_bps)| *share_bps as u32).sum();
+		if total_bps != 10_000 || splits.iter().any(|(_, share_bps)| *share_bps == 0) {
+			msg!("[TrustedProperties] ERROR: Rent shares must be non-zero & sum to 10000 bps, found {}", total_bps);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if let Some((payee, _)) = splits.iter().find(|(payee, _)| !rent_data.is_owner_payee(payee)) {
+			msg!("[TrustedProperties] ERROR: Payee {} is neither the owner nor a co-owner of the property", payee);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		require_financially_active(&rent_data)?;
+
+		if rent_data.is_token_denominated() {
+			msg!("[TrustedProperties] ERROR: Rent must be paid in tokens of the mint: {}", rent_data.payment_mint);
+			return Err(TrustedPropertiesError::InvalidInstruction.into());
+		}
+
+		if rent_data.autopay_remaining > 0 {
+			msg!("[TrustedProperties] ERROR: Rent is being paid by auto-pay ({} payments remaining)", rent_data.autopay_remaining);
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let payment_number = rent_data.payments_made.saturating_add(1);
+		let rent_due = amount_due_now(&rent_data, payment_number)?;
+
+		// The platform's share of the payment (if any) is taken first; the payees split the rest
+		let (platform_fee, payees_share) = platform_fee_split(&rent_data, payment_number, rent_due)?;
+
+		let mut payee_accounts = Vec::with_capacity(splits.len());
+		for (payee, _) in splits {
+			let payee_account = next_account(accounts_iter, "payee account")?;
+			if payee_account.key != payee {
+				msg!("[TrustedProperties] ERROR: Payee account {} does not match the split payee {}", payee_account.key, payee);
+				return Err(ProgramError::InvalidAccountData);
+			}
+			payee_accounts.push(payee_account);
+		}
+
+		if platform_fee > 0 {
+			let platform_wallet_account = platform_wallet_for(accounts_iter.next(), &rent_data)?;
+			transfer_with_balance_log(tenant_account, platform_wallet_account, system_program_account, platform_fee, "Platform fee")?;
+		}
+
+		// Transfer each payee's share; the last payee also receives the rounding remainder
+		let mut remaining_rent = payees_share;
+		for (i, (payee_account, (_, share_bps))) in payee_accounts.into_iter().zip(splits).enumerate() {
+			let share = if i == splits.len() - 1 {
+				remaining_rent
+			} else {
+				split_bps(payees_share, *share_bps).0
+			};
+			remaining_rent -= share;
+
+			transfer_with_balance_log(tenant_account, payee_account, system_program_account, share, "Rent share")?;
+		}
+
+		Self::record_rent_payment(&mut rent_data, payment_number)?;
+		warn_if_refund_uncovered(accounts, rent_agreement_account, &rent_data)?;
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+		emit_rent_paid(rent_agreement_account, &rent_data, payment_number, rent_due);
+
+		Ok(())
+	}
+
+
+	/// Waive the rent of some months, without any transfer (by Owner)
+	fn waive_rent(accounts: &[AccountInfo], program_id: &Pubkey, months: u8) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+
+		require_financially_active(&rent_data)?;
+
+		if months == 0 || months > rent_data.remaining_payments {
+			msg!("[TrustedProperties] ERROR: Months to waive ({}) must be between 1 and the remaining payments ({})", months, rent_data.remaining_payments);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		// The prepaid rent is tied to the upcoming payments
+		if rent_data.autopay_remaining > 0 {
+			msg!("[TrustedProperties] ERROR: Rent cannot be waived while auto-pay payments are pending ({})", rent_data.autopay_remaining);
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		rent_data.remaining_payments -= months;
+		rent_data.waived_payments = rent_data.waived_payments.saturating_add(months);
+		if rent_data.remaining_payments == 0 {
+			rent_data.status = AgreementStatus::Completed as u8;
+			rent_data.completed_ts = completion_ts(&rent_data);
+		}
+
+		warn_if_refund_uncovered(accounts, rent_agreement_account, &rent_data)?;
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Rent waived for {} months ({} payments remaining)", months, rent_data.remaining_payments);
+
+		Ok(())
+	}
+
+
+	/// Adjust the security deposit amount, settling the difference with the escrow (by both Owner and Tenant)
+	fn adjust_deposit(accounts: &[AccountInfo], program_id: &Pubkey, new_deposit: u64) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(escrow_account, "security escrow account")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		require_financially_active(&rent_data)?;
+
+		if new_deposit == rent_data.security_deposit {
+			msg!("[TrustedProperties] ERROR: New security deposit ({}) is the same as the current one", new_deposit);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+
+		if new_deposit > rent_data.security_deposit {
+			// Increase: the tenant pays the difference into the escrow
+			let increase = new_deposit - rent_data.security_deposit;
+			escrow.credit(increase)?;
+			escrow.total_deposited = escrow.total_deposited
+				.checked_add(Lamports(increase))
+				.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+
+			transfer_with_balance_log(tenant_account, escrow_account, system_program_account, increase, "Security deposit increase")?;
+		} else {
+			// Decrease: the deposit held above the new amount is refunded to the tenant
+			let refund = escrow.remaining_deposit.saturating_sub(Lamports(new_deposit));
+			transfer_from_program_account(escrow_account, tenant_account, refund.into())?;
+			escrow.debit(refund.into())?;
+
+			msg!("[TrustedProperties] Security deposit decrease: {} refunded to the tenant", refund);
+		}
+
+		msg!("[TrustedProperties] Security deposit adjusted from {} to {}. Remaining deposit in escrow: {}", rent_data.security_deposit, new_deposit, escrow.remaining_deposit);
+
+		escrow.security_deposit = Lamports(new_deposit);
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		rent_data.security_deposit = new_deposit;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Get the due date of the next rent payment & the time left until then (read-only)
+	fn get_next_due(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		if rent_data.remaining_payments == 0 {
+			msg!("[TrustedProperties] ERROR: No rent payment remaining for the agreement");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let now = clock_now()?;
+		let payment_number = rent_data.payments_made.saturating_add(1);
+		let due_ts = rent_data.due_ts_for_payment(payment_number);
+		let next_due = NextDue {
+			payment_number,
+			due_ts,
+			seconds_remaining: due_ts.saturating_sub(now).max(0),
+			overdue: rent_data.is_payment_late(payment_number, now),
+		};
+
+		msg!("[TrustedProperties] Next due: {:?}", next_due);
+
+		set_return_data(&next_due.try_to_vec()?);
+
+		Ok(())
+	}
+
+
+	/// Assign the tenancy to a new tenant (by both Owner and Tenant)
+	/// With `settle_deposit`, the new tenant pays a fresh deposit & the outgoing tenant is refunded the remaining one.
+	fn assign_tenancy(accounts: &[AccountInfo], program_id: &Pubkey, new_tenant_pubkey: Pubkey, settle_deposit: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(escrow_account, "security escrow account")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if !rent_data.is_financially_active() {
+			msg!("[TrustedProperties] ERROR: Agreement must be active to assign the tenancy");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		if new_tenant_pubkey == Pubkey::default() || new_tenant_pubkey == rent_data.tenant_pubkey || new_tenant_pubkey == rent_data.owner_pubkey {
+			msg!("[TrustedProperties] ERROR: New tenant ({}) must differ from the current tenant & the owner", new_tenant_pubkey);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		// The prepaid rent belongs to the current tenant
+		if rent_data.autopay_remaining > 0 {
+			msg!("[TrustedProperties] ERROR: Tenancy cannot be assigned while auto-pay payments are pending ({})", rent_data.autopay_remaining);
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+
+		if settle_deposit {
+			let new_tenant_account = next_account(accounts_iter, "new tenant signer")?;
+			let system_program_account = next_account(accounts_iter, "system program account")?;
+
+			if *new_tenant_account.key != new_tenant_pubkey || !new_tenant_account.is_signer {
+				msg!("[TrustedProperties] ERROR: New tenant ({}) must sign to pay the security deposit", new_tenant_pubkey);
+				return Err(ProgramError::MissingRequiredSignature);
+			}
+
+			// Collect the new deposit first, so that the escrow stays funded throughout
+			let outgoing_deposit = escrow.remaining_deposit;
+			transfer_with_balance_log(new_tenant_account, escrow_account, system_program_account, rent_data.security_deposit, "Security deposit (new tenant)")?;
+			transfer_from_program_account(escrow_account, tenant_account, outgoing_deposit.into())?;
+
+			escrow.debit(outgoing_deposit.into())?;
+			escrow.credit(rent_data.security_deposit)?;
+			escrow.total_deposited = escrow.total_deposited
+				.checked_add(Lamports(rent_data.security_deposit))
+				.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+
+			msg!("[TrustedProperties] Security deposit settled: {} refunded to the outgoing tenant, {} deposited by the new tenant", outgoing_deposit, rent_data.security_deposit);
+		}
+
+		escrow.tenant_pubkey = new_tenant_pubkey;
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Tenancy assigned from {} to {}", rent_data.tenant_pubkey, new_tenant_pubkey);
+
+		rent_data.tenant_pubkey = new_tenant_pubkey;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Sum the remaining obligations & deposits held across agreements of an owner into the accumulator account (by Owner)
+	fn accumulate_tvl(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let accumulator_account = next_account(accounts_iter, "accumulator account")?;
+		if accumulator_account.owner != program_id {
+			msg!("[TrustedProperties] Accumulator account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(accumulator_account, "accumulator account")?;
+
+		if accumulator_account.data_len() != PortfolioSummary::LEN {
+			msg!("[TrustedProperties] ERROR: Accumulator account data size ({}) must be {} bytes", accumulator_account.data_len(), PortfolioSummary::LEN);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		if !owner_account.is_signer {
+			msg!("[TrustedProperties] Owner must sign the portfolio summary");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		let previous_summary = PortfolioSummary::try_from_slice(&accumulator_account.data.borrow());
+		if previous_summary.is_err() {
+			msg!("[TrustedProperties] Accumulator account data size incorrect: {}", accumulator_account.try_data_len()?);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// An accumulator holds the summary of a single owner
+		let previous_owner = previous_summary.unwrap().owner_pubkey;
+		if previous_owner != Pubkey::default() && previous_owner != *owner_account.key {
+			msg!("[TrustedProperties] ERROR: Accumulator account holds the summary of another owner: {}", previous_owner);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		let mut summary = PortfolioSummary { owner_pubkey: *owner_account.key, ..PortfolioSummary::default() };
+
+		while let Some(rent_agreement_account) = accounts_iter.next() {
+			let escrow_account = next_account(accounts_iter, "escrow account (of the last agreement)")?;
+
+			if rent_agreement_account.owner != program_id || escrow_account.owner != program_id {
+				msg!("[TrustedProperties] Rent agreement {} or its escrow account is not owned by this program", rent_agreement_account.key);
+				return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+			}
+
+			let rent_data = load_agreement(rent_agreement_account)?;
+
+			if rent_data.owner_pubkey != *owner_account.key {
+				msg!("[TrustedProperties] ERROR: Rent agreement {} belongs to another owner: {}", rent_agreement_account.key, rent_data.owner_pubkey);
+				return Err(ProgramError::InvalidAccountData);
+			}
+
+			if rent_data.security_escrow_pubkey != *escrow_account.key {
+				msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one of the rent agreement {}", rent_agreement_account.key);
+				return Err(ProgramError::InvalidAccountData);
+			}
+
+			// The escrow state is only recorded once the deposit is made
+			let deposit_held: u64 = if rent_data.is_security_deposit_pending() {
+				0
+			} else {
+				load_escrow(escrow_account, rent_agreement_account, &rent_data)?.remaining_deposit.into()
+			};
+
+			summary.agreement_count += 1;
+			summary.remaining_obligation_lamports = summary.remaining_obligation_lamports.saturating_add(rent_data.remaining_obligation_lamports()?);
+			summary.deposits_held_lamports = summary.deposits_held_lamports.saturating_add(deposit_held);
+		}
+
+		msg!("[TrustedProperties] Portfolio summary: {:?}", summary);
+
+		pack_into(&summary, &mut accumulator_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Report a breach of the maximum occupancy clause (by Owner)
+	fn report_occupancy_breach(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+
+		if rent_data.max_occupants == 0 {
+			msg!("[TrustedProperties] ERROR: Agreement has no maximum occupancy clause");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		if !rent_data.is_financially_active() {
+			msg!("[TrustedProperties] ERROR: Agreement must be active to report an occupancy breach");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		rent_data.occupancy_breach = true;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Occupancy breach reported (maximum occupants: {})", rent_data.max_occupants);
+
+		Ok(())
+	}
+
+
+	/// Suspend an active agreement, or resume a suspended one (by both Owner and Tenant)
+	fn set_suspended(accounts: &[AccountInfo], program_id: &Pubkey, suspend: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		let now = clock_now()?;
+
+		if suspend {
+			require_financially_active(&rent_data)?;
+
+			rent_data.status = AgreementStatus::Suspended as u8;
+			rent_data.suspended_ts = now;
+		} else {
+			if !rent_data.is_suspended() {
+				msg!("[TrustedProperties] ERROR: Agreement is not suspended");
+				return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+			}
+
+			// Postpone the remaining due dates by the suspended period
+			rent_data.suspended_secs = rent_data.suspended_secs.saturating_add(now.saturating_sub(rent_data.suspended_ts).max(0));
+			rent_data.status = AgreementStatus::Active as u8;
+			rent_data.suspended_ts = 0;
+		}
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		if suspend {
+			msg!("[TrustedProperties] Agreement suspended at {}", now);
+		} else {
+			msg!("[TrustedProperties] Agreement resumed. Due dates postponed by {} seconds in total.", rent_data.suspended_secs);
+		}
+
+		Ok(())
+	}
+
+
+	/// Claim the remaining security deposit (escrow -> tenant) after the owner did not refund it in time (by Tenant)
+	fn claim_refund(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let ClaimRefundAccounts {
+			agreement: rent_agreement_account,
+			tenant: tenant_account,
+			escrow: escrow_account,
+		} = ClaimRefundAccounts::parse(accounts, program_id)?;
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		if rent_data.tenant_pubkey != *tenant_account.key {
+			msg!("[TrustedProperties] ERROR: Tenant's public-key (tenant_pubkey) does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// Disputed agreements are never completed, so this also rules out an outstanding dispute
+		if !rent_data.is_completed() {
+			msg!("[TrustedProperties] ERROR: Agreement must be completed to claim the security deposit refund");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		// The claim is always locked for the owner-response grace period: it requires the clock
+		let now = clock_now()?;
+		let refund_claim_unlock_ts = rent_data.refund_claim_unlock_ts()?;
+		if now < refund_claim_unlock_ts {
+			msg!("[TrustedProperties] ERROR: Security deposit refund claim is locked until {} (current time: {})", refund_claim_unlock_ts, now);
+			return Err(TrustedPropertiesError::RefundLocked.into());
+		}
+
+		if rent_data.is_condition_report_pending() {
+			msg!("[TrustedProperties] ERROR: Move-out condition report must be recorded before the security deposit refund");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+		let refund = escrow.available_balance();
+
+		transfer_from_program_account(escrow_account, tenant_account, refund)?;
+
+		escrow.debit(refund)?;
+		escrow.status = AgreementStatus::Completed as u8;
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Security deposit of {} lamports claimed by the tenant", refund);
+
+		Ok(())
+	}
+
+
+	/// Get the payment schedule (month, year, paid) of the whole agreement duration
+	fn get_schedule(accounts: &[AccountInfo], program_id: &Pubkey, require_party: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		if require_party {
+			check_party_signer(next_account(accounts_iter, "owner or tenant signer")?, &rent_data)?;
+		}
+
+		let schedule = rent_data.schedule();
+
+		msg!("[TrustedProperties] Schedule: {} payments, {} paid", schedule.len(), rent_data.payments_made);
+
+		set_return_data(&schedule.try_to_vec()?);
+
+		Ok(())
+	}
+
+
+	/// Get the role of a public-key in the agreement, or "none" when it is not a party to it
+	fn check_party(accounts: &[AccountInfo], program_id: &Pubkey, key: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let rent_data = load_agreement(rent_agreement_account)?;
+
+		let role = rent_data.party_role(key).unwrap_or("none");
+
+		msg!("[TrustedProperties] {} is party to the agreement as: {}", key, role);
+
+		set_return_data(role.as_bytes());
+
+		Ok(())
+	}
+
+
+	/// Renew a completed agreement into a fresh term, keeping the security deposit held (by both Owner and Tenant)
+	fn renew_agreement(accounts: &[AccountInfo], program_id: &Pubkey, duration: u8, rent_amount: u64, start_month: u8, start_year: u16) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		// A renewal is a new term: it is paused & validated like the initialization of a new agreement
+		let config = check_not_paused(next_account(accounts_iter, "program config account")?, program_id)?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		check_terms(&config, rent_amount, rent_data.security_deposit, duration, start_month, start_year)?;
+
+		require_not_sealed(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		// Only a completed lease can be renewed: not a terminated or disputed one
+		if !rent_data.is_completed() {
+			msg!("[TrustedProperties] ERROR: Only a completed agreement can be renewed (status: {})", rent_data.status);
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		// The renewed term keeps the held deposit: it must not have been refunded (or claimed) already
+		if load_escrow(escrow_account, rent_agreement_account, &rent_data)?.status == AgreementStatus::Completed as u8 {
+			msg!("[TrustedProperties] ERROR: Security deposit already refunded. Create a new agreement instead.");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		// Fresh term: the payment schedule restarts, the reputation history (late_payment_count) is kept
+		rent_data.status = AgreementStatus::Active as u8;
+		rent_data.duration = duration;
+		rent_data.remaining_payments = duration;
+		rent_data.payments_made = 0;
+		rent_data.waived_payments = 0;
+		rent_data.rent_amount = rent_amount;
+		rent_data.start_month = start_month;
+		rent_data.start_year = start_year;
+		rent_data.first_month_proration_bps = 0;
+		rent_data.completed_ts = 0;
+		rent_data.duration_extension_request = 0;
+		rent_data.extension_request_ts = 0;
+		rent_data.suspended_secs = 0;
+		rent_data.movein_report_hash = [0; 32];
+		rent_data.moveout_report_hash = [0; 32];
+		rent_data.occupancy_breach = false;
+		rent_data.last_op_nonce = 0;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] RENEWED: duration={} rent_amount={} start_month={} start_year={}", duration, rent_amount, start_month, start_year);
+
+		Ok(())
+	}
+
+
+	/// Resolve a dispute by splitting the remaining deposit between the tenant & owner, then resume or terminate the agreement (by Arbiter)
+	fn arbiter_resolve(accounts: &[AccountInfo], program_id: &Pubkey, tenant_share_bps: u16, terminate: bool) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let arbiter_account = next_account(accounts_iter, "arbiter signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant account")?;
+		let owner_account = next_account(accounts_iter, "owner account")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(escrow_account, "security escrow account")?;
+
+		if !arbiter_account.is_signer {
+			msg!("[TrustedProperties] Arbiter must sign the dispute resolution");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		if tenant_share_bps > 10_000 {
+			msg!("[TrustedProperties] ERROR: Tenant share ({} bps) must not exceed 10000 bps", tenant_share_bps);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		if !rent_data.has_arbiter() || rent_data.arbiter_pubkey != *arbiter_account.key {
+			msg!("[TrustedProperties] ERROR: Only the arbiter designated at the agreement initialization can resolve the dispute");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.owner_pubkey != *owner_account.key || rent_data.tenant_pubkey != *tenant_account.key {
+			msg!("[TrustedProperties] ERROR: Owner & Tenant public-keys do not match the ones used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if !rent_data.is_disputed() {
+			msg!("[TrustedProperties] ERROR: Agreement is not disputed");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+
+		let (tenant_share, owner_share) = split_bps(escrow.available_balance(), tenant_share_bps);
+
+		msg!("[TrustedProperties] Arbiter resolution: deposit {}, refunded to tenant: {}, paid to owner: {}, terminate: {}", escrow.remaining_deposit, tenant_share, owner_share, terminate);
+
+		transfer_from_program_account(escrow_account, tenant_account, tenant_share)?;
+		transfer_from_program_account(escrow_account, owner_account, owner_share)?;
+
+		escrow.debit(tenant_share)?;
+		escrow.debit(owner_share)?;
+		escrow.penalties_applied = escrow.penalties_applied.saturating_add(Lamports(owner_share));
+
+		if terminate {
+			// Rent prepaid for auto-pay is no longer needed
+			if rent_data.prepaid_rent > 0 {
+				msg!("[TrustedProperties] Refunding unused prepaid rent to the tenant: {}", rent_data.prepaid_rent);
+				transfer_from_program_account(escrow_account, tenant_account, rent_data.prepaid_rent)?;
+				rent_data.prepaid_rent = 0;
+				rent_data.autopay_remaining = 0;
+			}
+
+			escrow.status = AgreementStatus::Terminated as u8;
+			rent_data.remaining_payments = 0;
+			rent_data.status = AgreementStatus::Terminated as u8;
+		} else {
+			rent_data.status = AgreementStatus::Active as u8;
+		}
+
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Pay the overdue rent of several agreements of the tenant (tenant -> owners), skipping the ones not overdue
+	fn crank_overdue(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		if !tenant_account.is_signer {
+			msg!("[TrustedProperties] ERROR: Tenant must sign to pay the overdue rent");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		let system_program_account = next_account(accounts_iter, "system program account")?;
+
+		let triples = accounts_iter.as_slice();
+		if triples.is_empty() || triples.len() % 3 != 0 || triples.len() / 3 > MAX_BATCH_SIZE {
+			msg!("[TrustedProperties] ERROR: Expected 1 to {} triples of agreement, owner & escrow accounts, found {} accounts", MAX_BATCH_SIZE, triples.len());
+			return Err(ProgramError::NotEnoughAccountKeys);
+		}
+
+		let now = clock_now()?;
+		let mut summary = CrankOverdueSummary::default();
+
+		for triple in triples.chunks(3) {
+			let (rent_agreement_account, owner_account, escrow_account) = (&triple[0], &triple[1], &triple[2]);
+
+			if rent_agreement_account.owner != program_id {
+				msg!("[TrustedProperties] ERROR: Rent agreement account {} is not owned by this program", rent_agreement_account.key);
+				return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+			}
+			check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+			let mut rent_data = load_agreement(rent_agreement_account)?;
+
+			if rent_data.tenant_pubkey != *tenant_account.key
+				|| rent_data.owner_pubkey != *owner_account.key
+				|| rent_data.security_escrow_pubkey != *escrow_account.key
+			{
+				msg!("[TrustedProperties] Agreement {} skipped: not between the tenant & the given owner, with the given escrow", rent_agreement_account.key);
+				summary.agreements_skipped += 1;
+				continue;
+			}
+
+			// Only plain lamport payments from the tenant to the owner can be made by this crank
+			if !rent_data.is_financially_active()
+				|| rent_data.is_token_denominated()
+				|| rent_data.autopay_remaining > 0
+				|| rent_data.platform_fee_bps > 0
+				|| tenant_account.key == owner_account.key
+			{
+				msg!("[TrustedProperties] Agreement {} skipped: not payable by the crank (status: {})", rent_agreement_account.key, rent_data.status_label());
+				summary.agreements_skipped += 1;
+				continue;
+			}
+
+			// Exactly the overdue payments, late fees included
+			let first_payment = rent_data.payments_made.saturating_add(1);
+			let last_payment = rent_data.payments_made.saturating_add(rent_data.remaining_payments);
+			let mut overdue_payments: u8 = 0;
+			let mut amount_due: u64 = 0;
+			for n in first_payment..=last_payment {
+				if !rent_data.is_payment_late(n, now) {
+					break;
+				}
+				amount_due = amount_due
+					.checked_add(rent_data.amount_due_for_payment(n, now)?)
+					.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+				overdue_payments += 1;
+			}
+
+			if overdue_payments == 0 {
+				msg!("[TrustedProperties] Agreement {} skipped: no payment overdue", rent_agreement_account.key);
+				summary.agreements_skipped += 1;
+				continue;
+			}
+
+			if tenant_account.lamports() < amount_due {
+				msg!("[TrustedProperties] Agreement {} skipped: {} overdue payments ({}) exceed the tenant's balance ({})", rent_agreement_account.key, overdue_payments, amount_due, tenant_account.lamports());
+				summary.agreements_skipped += 1;
+				continue;
+			}
+
+			transfer_with_balance_log(tenant_account, owner_account, system_program_account, amount_due, "Overdue rent")?;
+
+			for i in 0..overdue_payments {
+				Self::record_rent_payment(&mut rent_data, first_payment + i)?;
+			}
+			warn_if_refund_uncovered(triple, rent_agreement_account, &rent_data)?;
+
+			check_rent_exempt(rent_agreement_account)?;
+			rent_data.next_event_seq()?;
+			rent_data.validate_invariants()?;
+			pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+			#[cfg(feature = "anchor-events")]
+			for n in first_payment..first_payment + overdue_payments {
+				emit_rent_paid(rent_agreement_account, &rent_data, n, rent_data.amount_due_for_payment(n, now)?);
+			}
+
+			msg!("[TrustedProperties] Agreement {}: {} overdue payments made ({})", rent_agreement_account.key, overdue_payments, amount_due);
+			summary.agreements_paid += 1;
+			summary.payments_made += overdue_payments as u16;
+			summary.total_paid_lamports = summary.total_paid_lamports
+				.checked_add(amount_due)
+				.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		}
+
+		msg!("[TrustedProperties] Overdue crank: {} agreements paid, {} skipped, {} payments ({})", summary.agreements_paid, summary.agreements_skipped, summary.payments_made, summary.total_paid_lamports);
+		set_return_data(&summary.try_to_vec()?);
+
+		Ok(())
+	}
+
+
+	/// Seal a completed or terminated agreement as a permanent record (by both Owner and Tenant)
+	fn finalize_record(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_sealed(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if !rent_data.is_finalized() {
+			msg!("[TrustedProperties] ERROR: Only a completed or terminated agreement can be sealed (status: {})", rent_data.status_label());
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		rent_data.is_sealed = true;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		msg!("[TrustedProperties] Agreement {} sealed as a permanent record (status: {})", rent_agreement_account.key, rent_data.status_label());
+
+		Ok(())
+	}
+
+
+	/// Merge the second agreement into the first one & close it (by both Owner and Tenant)
+	fn merge_agreements(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let merged_agreement_account = next_account(accounts_iter, "merged rent agreement account")?;
+		if merged_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Merged rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(merged_agreement_account, "merged rent agreement account")?;
+
+		if rent_agreement_account.key == merged_agreement_account.key {
+			msg!("[TrustedProperties] ERROR: Cannot merge an agreement into itself");
+			return Err(ProgramError::InvalidArgument);
+		}
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+		let merged_escrow_account = next_account(accounts_iter, "merged escrow account")?;
+
+		if escrow_account.owner != program_id || merged_escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(escrow_account, "security escrow account")?;
+		check_writable_state(merged_escrow_account, "merged security escrow account")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+		let merged_data = load_agreement(merged_agreement_account)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		if merged_data.owner_pubkey != rent_data.owner_pubkey || merged_data.tenant_pubkey != rent_data.tenant_pubkey {
+			msg!("[TrustedProperties] ERROR: Only agreements between the same owner & tenant can be merged");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key || merged_data.security_escrow_pubkey != *merged_escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		require_financially_active(&rent_data)?;
+		require_financially_active(&merged_data)?;
+
+		if merged_data.rent_amount != rent_data.rent_amount || merged_data.payment_mint != rent_data.payment_mint {
+			msg!("[TrustedProperties] ERROR: Agreements with different rent amounts ({} & {}) cannot be merged", rent_data.rent_amount, merged_data.rent_amount);
+			return Err(TrustedPropertiesError::IncorrectPaymentAmount.into());
+		}
+
+		// The prepaid rent is tied to the upcoming payments of each agreement
+		if rent_data.autopay_remaining > 0 || merged_data.autopay_remaining > 0 || rent_data.prepaid_rent > 0 || merged_data.prepaid_rent > 0 {
+			msg!("[TrustedProperties] ERROR: Agreements cannot be merged while auto-pay payments are pending or prepaid rent is held");
+			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		}
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+		let mut merged_escrow = load_escrow(merged_escrow_account, merged_agreement_account, &merged_data)?;
+
+		// The remaining term of the second agreement extends the first one
+		let merged_payments = merged_data.remaining_payments;
+		rent_data.remaining_payments = rent_data.remaining_payments
+			.checked_add(merged_payments)
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		rent_data.duration = rent_data.duration
+			.checked_add(merged_payments)
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		check_term_years(rent_data.start_month, rent_data.start_year, rent_data.duration)?;
+
+		// Move the security deposit of the second agreement to the first escrow
+		let merged_deposit = u64::from(merged_escrow.remaining_deposit);
+		transfer_from_program_account(merged_escrow_account, escrow_account, merged_deposit)?;
+		merged_escrow.debit(merged_deposit)?;
+		merged_escrow.closed = true;
+		escrow.credit(merged_deposit)?;
+		escrow.total_deposited = escrow.total_deposited
+			.checked_add(Lamports(merged_deposit))
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		escrow.security_deposit = escrow.security_deposit
+			.checked_add(Lamports(merged_data.security_deposit))
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		rent_data.security_deposit = u64::from(escrow.security_deposit);
+		pack_into(&merged_escrow, &mut merged_escrow_account.data.borrow_mut())?;
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		// Close the second agreement account: reclaim its lamports to the owner & wipe its state
+		let reclaimed = merged_agreement_account.lamports();
+		transfer_from_program_account(merged_agreement_account, owner_account, reclaimed)?;
+		merged_agreement_account.data.borrow_mut().fill(0);
+
+		msg!("[TrustedProperties] Agreement {} merged into {}: {} payments & {} deposit added, {} lamports reclaimed by the owner", merged_agreement_account.key, rent_agreement_account.key, merged_payments, merged_deposit, reclaimed);
+
+		Ok(())
+	}
+
+
+	/// Force a stuck agreement to `Terminated` or `Completed`, refunding the deposit to the tenant (by the program authority)
+	fn admin_force_resolve(accounts: &[AccountInfo], program_id: &Pubkey, final_status: u8) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let authority_account = next_account(accounts_iter, "program authority signer")?;
+		let config_account = next_account(accounts_iter, "program config account")?;
+		let tenant_account = next_account(accounts_iter, "tenant account")?;
+		let escrow_account = next_account(accounts_iter, "escrow account")?;
+
+		if escrow_account.owner != program_id {
+			msg!("[TrustedProperties] Security escrow account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(escrow_account, "security escrow account")?;
+
+		if !authority_account.is_signer {
+			msg!("[TrustedProperties] ERROR: Program authority must sign the forced resolution");
+			return Err(ProgramError::MissingRequiredSignature);
+		}
+
+		let (config_pda, _) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+		if config_pda != *config_account.key {
+			msg!("[TrustedProperties] ERROR: Program config account does not match the derived config PDA: {}", config_pda);
+			return Err(ProgramError::InvalidSeeds);
+		}
+
+		if config_account.owner != program_id {
+			msg!("[TrustedProperties] ERROR: Program config account must be owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+
+		let config_data = ProgramConfig::try_from_slice_versioned(&config_account.data.borrow());
+		if config_data.is_err() {
+			msg!("[TrustedProperties] ERROR: Program config account data size incorrect: {}", config_account.try_data_len()?);
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if config_data.unwrap().authority != *authority_account.key {
+			msg!("[TrustedProperties] ERROR: Only the program authority can force-resolve an agreement");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if final_status != AgreementStatus::Terminated as u8 && final_status != AgreementStatus::Completed as u8 {
+			msg!("[TrustedProperties] ERROR: Final status ({}) must be Terminated ({}) or Completed ({})", final_status, AgreementStatus::Terminated as u8, AgreementStatus::Completed as u8);
+			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		}
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		if rent_data.tenant_pubkey != *tenant_account.key {
+			msg!("[TrustedProperties] ERROR: Tenant's public-key (tenant_pubkey) does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		if rent_data.security_escrow_pubkey != *escrow_account.key {
+			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
+			return Err(ProgramError::InvalidAccountData);
+		}
+
+		require_not_finalized(&rent_data)?;
+
+		let mut escrow = load_escrow(escrow_account, rent_agreement_account, &rent_data)?;
+		let refund = escrow.available_balance();
+
+		let final_label = AgreementStatus::try_from(final_status)?.label();
+		msg!("[TrustedProperties] !!! ADMIN ACTION !!! Program authority {} force-resolving agreement {} from {} to {}", authority_account.key, rent_agreement_account.key, rent_data.status_label(), final_label);
+		msg!("[TrustedProperties] !!! ADMIN ACTION !!! Deposit refunded to the tenant: {}, prepaid rent refunded: {}", refund, rent_data.prepaid_rent);
+
+		// Default deposit disposition: everything held in the escrow for the tenant goes back to the tenant
+		transfer_from_program_account(escrow_account, tenant_account, refund)?;
+		escrow.debit(refund)?;
+		escrow.status = final_status;
+		pack_into(&escrow, &mut escrow_account.data.borrow_mut())?;
+
+		if rent_data.prepaid_rent > 0 {
+			transfer_from_program_account(escrow_account, tenant_account, rent_data.prepaid_rent)?;
+			rent_data.prepaid_rent = 0;
+			rent_data.autopay_remaining = 0;
+		}
+
+		rent_data.remaining_payments = 0;
+		rent_data.status = final_status;
+		if final_status == AgreementStatus::Completed as u8 {
+			rent_data.completed_ts = clock_now()?;
+		}
+
+		warn_if_refund_uncovered(accounts, rent_agreement_account, &rent_data)?;
+
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+
+		Ok(())
+	}
+
+
+	/// Record the next monthly payment as made outside the program, without any transfer (by both Owner and Tenant)
+	fn record_external_payment(accounts: &[AccountInfo], program_id: &Pubkey, reference: [u8; 32]) -> ProgramResult {
+
+		let accounts_iter = &mut accounts.iter();
+
+		let rent_agreement_account = next_account(accounts_iter, "rent agreement account")?;
+		if rent_agreement_account.owner != program_id {
+			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
+			return Err(TrustedPropertiesError::InvalidAccountOwner.into());
+		}
+		check_writable_state(rent_agreement_account, "rent agreement account")?;
+
+		let owner_account = next_account(accounts_iter, "owner signer")?;
+		let tenant_account = next_account(accounts_iter, "tenant signer")?;
+
+		let mut rent_data = load_agreement(rent_agreement_account)?;
+
+		require_not_finalized(&rent_data)?;
+
+		require_signing_party(owner_account, &rent_data.owner_pubkey, TrustedPropertiesError::OwnerMismatch)?;
+		require_signing_party(tenant_account, &rent_data.tenant_pubkey, TrustedPropertiesError::TenantMismatch)?;
+
+		require_financially_active(&rent_data)?;
+
+		let payment_number = rent_data.payments_made.saturating_add(1);
+		Self::record_rent_payment(&mut rent_data, payment_number)?;
+		warn_if_refund_uncovered(accounts, rent_agreement_account, &rent_data)?;
+		rent_data.external_payment_count = rent_data.external_payment_count.saturating_add(1);
+		rent_data.external_payment_ref = reference;
+		check_rent_exempt(rent_agreement_account)?;
+		rent_data.next_event_seq()?;
+		rent_data.validate_invariants()?;
+		pack_into(&rent_data, &mut rent_agreement_account.data.borrow_mut())?;
+		emit_rent_paid(rent_agreement_account, &rent_data, payment_number, 0);
+
+		msg!("[TrustedProperties] Payment #{} recorded as made outside the program ({} external payments)", payment_number, rent_data.external_payment_count);
+
+		Ok(())
+	}
+
+}
+
+
+/* ==========================================================================
+					Test Helpers: In-Memory Processor Driver
+============================================================================= */
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::state::{month_start_ts, test_utils::TestAccount};
+	use solana_program::{
+		entrypoint::{ProcessInstruction, SUCCESS},
+		program_error::UNSUPPORTED_SYSVAR,
+		instruction::{AccountMeta, Instruction},
+		program_stubs::{self, SyscallStubs},
+	};
+	use std::{cell::{Cell, RefCell}, collections::HashMap, convert::TryInto, sync::Once};
+
+	thread_local! {
+		/// Current time of the stubbed Clock sysvar, per test (each test runs on a thread of its own).
+		/// None simulates an older runtime without the sysvar.
+		static NOW: Cell<Option<i64>> = Cell::new(Some(month_start_ts(1, 2022)));
+
+		/// Lines logged by the program, per test, as the runtime would log them
+		static LOGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+	}
+
+	/// The current time of the stubbed Clock sysvar: the start of January 2022, unless moved by `set_now` (or unset)
+	fn now() -> Option<i64> {
+		NOW.with(|now| now.get())
+	}
+
+	/// Move the stubbed clock, eg: past a delay
+	fn set_now(unix_timestamp: i64) {
+		NOW.with(|now| now.set(Some(unix_timestamp)));
+	}
+
+	/// Make the stubbed Clock sysvar unavailable, until the clock is set again
+	fn unset_clock() {
+		NOW.with(|now| now.set(None));
+	}
+
+	/// Take the lines logged by the program so far
+	#[cfg(feature = "anchor-events")]
+	fn take_logs() -> Vec<String> {
+		LOGS.with(|logs| logs.take())
+	}
+
+	/// Program id of the mock caller program, invoking this program via CPI
+	fn mock_caller_id() -> Pubkey {
+		Pubkey::new(&[7; 32])
+	}
+
+	/// Seed of the mock caller's PDA owning the agreements it initializes
+	const OWNER_SEED: &[u8] = b"owner";
+
+	/// Syscalls of the runtime, stubbed for a native test: the test's clock, the captured logs, the default rent,
+	/// system transfers & account creations, the SPL Token program & the invocations of this program by the mock caller (the only CPIs made by the tested paths)
+	struct TestSyscalls;
+
+	impl SyscallStubs for TestSyscalls {
+		fn sol_log(&self, message: &str) {
+			println!("{}", message);
+			LOGS.with(|logs| logs.borrow_mut().push(format!("Program log: {}", message)));
+		}
+
+		fn sol_log_data(&self, fields: &[&[u8]]) {
+			let data: Vec<String> = fields.iter().map(base64::encode).collect();
+			let line = format!("Program data: {}", data.join(" "));
+			println!("{}", line);
+			LOGS.with(|logs| logs.borrow_mut().push(line));
+		}
+
+		fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+			let unix_timestamp = match now() {
+				Some(unix_timestamp) => unix_timestamp,
+				None => return UNSUPPORTED_SYSVAR,
+			};
+			unsafe { *(var_addr as *mut Clock) = Clock { unix_timestamp, ..Clock::default() } };
+			SUCCESS
+		}
+
+		fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+			unsafe { *(var_addr as *mut Rent) = Rent::default() };
+			SUCCESS
+		}
+
+		fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+			let find = |key: &Pubkey| account_infos.iter().find(|info| info.key == key).expect("account of the invoked instruction");
+
+			// Any other program is the SPL Token program, or this one invoked by the mock caller: its PDAs sign with the seeds passed
+			if instruction.program_id != system_program::id() {
+				let signer_pdas = signers_seeds
+					.iter()
+					.map(|seeds| Pubkey::create_program_address(seeds, &mock_caller_id()))
+					.collect::<Result<Vec<Pubkey>, _>>()?;
+
+				let infos = instruction.accounts
+					.iter()
+					.map(|meta| {
+						let mut info = find(&meta.pubkey).clone();
+						if meta.is_signer && !info.is_signer && !signer_pdas.contains(info.key) {
+							return Err(ProgramError::MissingRequiredSignature);
+						}
+						info.is_signer = meta.is_signer;
+						info.is_writable = meta.is_writable;
+						Ok(info)
+					})
+					.collect::<Result<Vec<AccountInfo>, ProgramError>>()?;
+
+				if instruction.program_id == spl_token::id() {
+					return spl_token::processor::Processor::process(&instruction.program_id, &infos, &instruction.data);
+				}
+				return Processor::process(&instruction.program_id, &infos, &instruction.data);
+			}
+
+			let (lamports, created) = system_instruction_fields(&instruction.data);
+			let from = find(&instruction.accounts[0].pubkey);
+			let to = find(&instruction.accounts[1].pubkey);
+			if !from.is_signer {
+				return Err(ProgramError::MissingRequiredSignature);
+			}
+
+			let from_balance = from.lamports().checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+			**from.try_borrow_mut_lamports()? = from_balance;
+			**to.try_borrow_mut_lamports()? += lamports;
+
+			// The data of a native account cannot grow: the account to create is allocated by the test, only its owner is set
+			if let Some((space, owner)) = created {
+				assert_eq!(to.data_len() as u64, space, "account to create must be allocated by the test");
+				to.assign(&owner);
+			}
+			Ok(())
+		}
+	}
+
+	/// Lamports of a system `Transfer` (variant 2) or `CreateAccount` (variant 0) instruction, with the space & owner of the created account.
+	/// Serialized as the `u32` variant index followed by the `u64` lamports (then the `u64` space & the owner, to create an account).
+	fn system_instruction_fields(data: &[u8]) -> (u64, Option<(u64, Pubkey)>) {
+		let variant = u32::from_le_bytes(data[..4].try_into().unwrap());
+		let lamports = u64::from_le_bytes(data[4..12].try_into().unwrap());
+		match variant {
+			0 => (lamports, Some((u64::from_le_bytes(data[12..20].try_into().unwrap()), Pubkey::new(&data[20..52])))),
+			2 => (lamports, None),
+			_ => panic!("only system transfers & account creations are stubbed"),
+		}
+	}
+
+	/// In-memory bank of accounts, processing instructions against them like the runtime would:
+	/// every instruction borrows the accounts it lists, and its lamport & data mutations carry over to the next one.
+	struct ProgramDriver {
+		program_id: Pubkey,
+		accounts: Vec<TestAccount>,
+	}
+
+	impl ProgramDriver {
+
+		/// A bank holding the system program, the Rent sysvar & the (empty) program config PDA
+		fn new() -> Self {
+			static STUBS: Once = Once::new();
+			STUBS.call_once(|| {
+				program_stubs::set_syscall_stubs(Box::new(TestSyscalls));
+			});
+
+			let program_id = Pubkey::new_unique();
+			let mut driver = ProgramDriver { program_id, accounts: Vec::new() };
+
+			let mut system_program_account = TestAccount::new(Pubkey::default(), 1, vec![]);
+			system_program_account.key = system_program::id();
+			driver.add(system_program_account);
+
+			let mut rent_sysvar_account = TestAccount::new(sysvar::id(), 1, vec![0; Rent::size_of()]);
+			rent_sysvar_account.key = sysvar::rent::id();
+			Rent::default().to_account_info(&mut rent_sysvar_account.account_info()).unwrap();
+			driver.add(rent_sysvar_account);
+
+			let mut config_account = TestAccount::new(system_program::id(), 0, vec![]);
+			config_account.key = driver.config_pda();
+			driver.add(config_account);
+
+			driver
+		}
+
+		fn config_pda(&self) -> Pubkey {
+			Pubkey::find_program_address(&[CONFIG_SEED], &self.program_id).0
+		}
+
+		/// Add an account to the bank, returning its key
+		fn add(&mut self, account: TestAccount) -> Pubkey {
+			let key = account.key;
+			self.accounts.push(account);
+			key
+		}
+
+		/// Add an empty account of `len` zeroed bytes owned by this program & funded to be rent exempt (eg: created by the client)
+		fn add_program_account(&mut self, len: usize) -> Pubkey {
+			let lamports = Rent::default().minimum_balance(len);
+			self.add(TestAccount::new(self.program_id, lamports, vec![0; len]))
+		}
+
+		/// Add a wallet holding `lamports`
+		fn add_wallet(&mut self, lamports: u64) -> Pubkey {
+			self.add(TestAccount::new(system_program::id(), lamports, vec![]))
+		}
+
+		fn account(&self, key: &Pubkey) -> &TestAccount {
+			self.accounts.iter().find(|account| account.key == *key).expect("account in the bank")
+		}
+
+		fn account_mut(&mut self, key: &Pubkey) -> &mut TestAccount {
+			self.accounts.iter_mut().find(|account| account.key == *key).expect("account in the bank")
+		}
+
+		fn lamports(&self, key: &Pubkey) -> u64 {
+			self.account(key).lamports
+		}
+
+		fn agreement(&self, key: &Pubkey) -> RentAgreementAccount {
+			RentAgreementAccount::try_from_slice_versioned(&self.account(key).data).unwrap()
+		}
+
+		fn escrow(&self, key: &Pubkey) -> SecurityEscrowAccount {
+			SecurityEscrowAccount::try_from_slice(&self.account(key).data).unwrap()
+		}
+
+		/// Process an instruction of this program, as a transaction of its own:
+		/// a failing instruction leaves every account (lamports & data) as it was, as in the runtime
+		fn process(&mut self, accounts: Vec<AccountMeta>, data: Vec<u8>) -> ProgramResult {
+			let snapshot: Vec<(u64, Vec<u8>)> = self.accounts
+				.iter()
+				.map(|account| (account.lamports, account.data.clone()))
+				.collect();
+
+			let result = self.process_program(self.program_id, Processor::process, accounts, data);
+			if result.is_err() {
+				for (account, (lamports, data)) in self.accounts.iter_mut().zip(snapshot) {
+					account.lamports = lamports;
+					account.data = data;
+				}
+			}
+			result
+		}
+
+		/// Process the instruction with the accounts it lists (flagged signer & writable as in its metas).
+		/// A key listed twice is the same account (sharing its lamports & data), as in the runtime.
+		fn process_program(&mut self, program_id: Pubkey, process_instruction: ProcessInstruction, accounts: Vec<AccountMeta>, data: Vec<u8>) -> ProgramResult {
+			let infos: HashMap<Pubkey, AccountInfo> = self.accounts
+				.iter_mut()
+				.map(|account| (account.key, account.account_info()))
+				.collect();
+
+			let account_infos: Vec<AccountInfo> = accounts
+				.iter()
+				.map(|meta| {
+					let mut info = infos[&meta.pubkey].clone();
+					info.is_signer = meta.is_signer;
+					info.is_writable = meta.is_writable;
+					info
+				})
+				.collect();
+
+			process_instruction(&program_id, &account_infos, &data)
+		}
+	}
+
+	/// Instruction data of `InitializeRentContract` (tag 0), packed as `InitParams::unpack` reads it
+	fn initialize_data(params: &InitParams) -> Vec<u8> {
+		let mut data = vec![0];
+		data.extend_from_slice(params.owner_pubkey.as_ref());
+		data.extend_from_slice(params.tenant_pubkey.as_ref());
+		data.extend_from_slice(params.security_escrow_pubkey.as_ref());
+		data.extend_from_slice(&params.security_deposit.to_le_bytes());
+		data.extend_from_slice(&params.rent_amount.to_le_bytes());
+		data.push(params.duration);
+		data.push(params.start_month);
+		data.extend_from_slice(&params.start_year.to_le_bytes());
+		data.extend_from_slice(&params.annual_increase_bps.to_le_bytes());
+		data.extend_from_slice(&params.refund_delay_days.to_le_bytes());
+		data.extend_from_slice(params.payment_mint.as_ref());
+		data.extend_from_slice(&params.first_month_proration_bps.to_le_bytes());
+		data.extend_from_slice(&params.late_fee.to_le_bytes());
+		data.push(params.max_occupants);
+		data.extend_from_slice(&params.platform_fee_bps.to_le_bytes());
+		data.extend_from_slice(params.platform_wallet.as_ref());
+		data.extend_from_slice(params.arbiter_pubkey.as_ref());
+		data.push(params.allow_self_rent as u8);
+		data.push(params.payment_window_days);
+		assert_eq!(data.len(), 1 + InitParams::LEN);
+		data
+	}
+
+	/// The parties & accounts of an agreement set up in a driver, before its initialization
+	struct Agreement {
+		agreement: Pubkey,
+		owner: Pubkey,
+		tenant: Pubkey,
+		escrow: Pubkey,
+	}
+
+	const RENT: u64 = 1_000_000;
+	const DEPOSIT: u64 = 2_000_000;
+
+	fn setup(driver: &mut ProgramDriver) -> Agreement {
+		Agreement {
+			agreement: driver.add_program_account(RentAgreementAccount::LEN),
+			owner: driver.add_wallet(0),
+			tenant: driver.add_wallet(10 * RENT),
+			escrow: driver.add_program_account(SecurityEscrowAccount::LEN),
+		}
+	}
+
+	/// Terms of a two months agreement starting now, without any optional term
+	fn init_params(agreement: &Agreement) -> InitParams {
+		InitParams {
+			owner_pubkey: agreement.owner,
+			tenant_pubkey: agreement.tenant,
+			security_escrow_pubkey: agreement.escrow,
+			security_deposit: DEPOSIT,
+			rent_amount: RENT,
+			duration: 2,
+			start_month: 1,
+			start_year: 2022,
+			annual_increase_bps: 0,
+			refund_delay_days: 0,
+			payment_mint: Pubkey::default(),
+			first_month_proration_bps: 0,
+			late_fee: 0,
+			max_occupants: 0,
+			platform_fee_bps: 0,
+			platform_wallet: Pubkey::default(),
+			arbiter_pubkey: Pubkey::default(),
+			allow_self_rent: false,
+			payment_window_days: 0,
+		}
+	}
+
+	fn initialize(driver: &mut ProgramDriver, agreement: &Agreement, params: &InitParams) -> ProgramResult {
+		let config = driver.config_pda();
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+				AccountMeta::new_readonly(config, false),
+			],
+			initialize_data(params),
+		)
+	}
+
+	fn pay_rent(driver: &mut ProgramDriver, agreement: &Agreement, op_nonce: u64) -> ProgramResult {
+		pay_rent_with(driver, agreement, RENT, op_nonce, None)
+	}
+
+	fn pay_rent_with(driver: &mut ProgramDriver, agreement: &Agreement, rent_amount: u64, op_nonce: u64, platform_wallet: Option<Pubkey>) -> ProgramResult {
+		let mut data = vec![2];
+		data.extend_from_slice(&rent_amount.to_le_bytes());
+		data.extend_from_slice(&op_nonce.to_le_bytes());
+		let mut accounts = vec![
+			AccountMeta::new(agreement.agreement, false),
+			AccountMeta::new(agreement.tenant, true),
+			AccountMeta::new(agreement.owner, false),
+			AccountMeta::new_readonly(system_program::id(), false),
+		];
+		accounts.extend(platform_wallet.map(|wallet| AccountMeta::new(wallet, false)));
+		accounts.push(AccountMeta::new(agreement.escrow, false));
+		driver.process(accounts, data)
+	}
+
+	fn deposit(driver: &mut ProgramDriver, agreement: &Agreement) -> ProgramResult {
+		let mut data = vec![1];
+		data.extend_from_slice(&DEPOSIT.to_le_bytes());
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new(agreement.escrow, false),
+				AccountMeta::new_readonly(system_program::id(), false),
+			],
+			data,
+		)
+	}
+
+	fn refund(driver: &mut ProgramDriver, agreement: &Agreement) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new_readonly(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+				AccountMeta::new(agreement.tenant, false),
+				AccountMeta::new(agreement.escrow, false),
+			],
+			vec![6],
+		)
+	}
+
+	/// An agreement initialized (with the default terms) & its security deposited: active
+	fn active_agreement(driver: &mut ProgramDriver) -> Agreement {
+		active_agreement_with(driver, |_| {})
+	}
+
+	/// An agreement initialized with the default terms changed by `terms`, & its security deposited: active
+	fn active_agreement_with(driver: &mut ProgramDriver, terms: impl FnOnce(&mut InitParams)) -> Agreement {
+		let agreement = setup(driver);
+		let mut params = init_params(&agreement);
+		terms(&mut params);
+		initialize(driver, &agreement, &params).unwrap();
+		deposit(driver, &agreement).unwrap();
+		agreement
+	}
+
+	#[test]
+	fn test_agreement_lifecycle() {
+		let mut driver = ProgramDriver::new();
+		let agreement = setup(&mut driver);
+		let escrow_rent_exempt = driver.lamports(&agreement.escrow);
+
+		// 1. Initialize: pending the security deposit
+		initialize(&mut driver, &agreement, &init_params(&agreement)).unwrap();
+		let state = driver.agreement(&agreement.agreement);
+		assert_eq!(state.status, AgreementStatus::DepositPending as u8);
+		assert_eq!((state.owner_pubkey, state.tenant_pubkey, state.security_escrow_pubkey), (agreement.owner, agreement.tenant, agreement.escrow));
+		assert_eq!((state.duration, state.remaining_payments, state.payments_made), (2, 2, 0));
+		assert_eq!(state.event_seq, 1);
+
+		// 2. Deposit the security (tenant -> escrow): active
+		deposit(&mut driver, &agreement).unwrap();
+		let state = driver.agreement(&agreement.agreement);
+		assert_eq!(state.status, AgreementStatus::Active as u8);
+		assert_eq!(state.event_seq, 2);
+		assert_eq!(driver.lamports(&agreement.tenant), 10 * RENT - DEPOSIT);
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_rent_exempt + DEPOSIT);
+		let escrow = driver.escrow(&agreement.escrow);
+		assert_eq!(escrow.agreement_pubkey, agreement.agreement);
+		assert_eq!(escrow.remaining_deposit, Lamports(DEPOSIT));
+
+		// 3. Pay the first month's rent (tenant -> owner)
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		let state = driver.agreement(&agreement.agreement);
+		assert_eq!(state.status, AgreementStatus::Active as u8);
+		assert_eq!((state.remaining_payments, state.payments_made, state.last_op_nonce), (1, 1, 1));
+		assert_eq!(state.event_seq, 3);
+		assert_eq!(driver.lamports(&agreement.owner), RENT);
+
+		// 4. Pay the last month's rent: completed
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+		let state = driver.agreement(&agreement.agreement);
+		assert_eq!(state.status, AgreementStatus::Completed as u8);
+		assert_eq!((state.remaining_payments, state.payments_made), (0, 2));
+		assert_eq!(Some(state.completed_ts), now());
+		assert_eq!(state.event_seq, 4);
+		assert_eq!(driver.lamports(&agreement.owner), 2 * RENT);
+		assert_eq!(driver.lamports(&agreement.tenant), 8 * RENT - DEPOSIT);
+
+		// 5. Refund the security deposit (escrow -> tenant)
+		refund(&mut driver, &agreement).unwrap();
+		assert_eq!(driver.lamports(&agreement.tenant), 8 * RENT);
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_rent_exempt);
+		let escrow = driver.escrow(&agreement.escrow);
+		assert_eq!(escrow.remaining_deposit, Lamports(0));
+		assert_eq!(escrow.status, AgreementStatus::Completed as u8);
+	}
+
+	#[test]
+	fn test_initialize_twice_fails() {
+		let mut driver = ProgramDriver::new();
+		let agreement = setup(&mut driver);
+
+		initialize(&mut driver, &agreement, &init_params(&agreement)).unwrap();
+		let stored = driver.account(&agreement.agreement).data.clone();
+
+		// A second initialization, even with other terms, must not overwrite the agreement
+		let params = InitParams { rent_amount: 1, security_deposit: 1, duration: 12, ..init_params(&agreement) };
+		assert_eq!(initialize(&mut driver, &agreement, &params), Err(ProgramError::AccountAlreadyInitialized));
+		assert_eq!(driver.account(&agreement.agreement).data, stored);
+
+		let state = driver.agreement(&agreement.agreement);
+		assert_eq!((state.rent_amount, state.security_deposit, state.duration), (RENT, DEPOSIT, 2));
+	}
+
+	/// A parent program (eg: a marketplace) initializing an agreement owned by its PDA, signing for it when its first data byte is set.
+	/// The rest of the data is the `InitializeRentContract` instruction data.
+	///
+	/// Accounts expected:
+	/// 0. `[writable]` The Rent Agreement account
+	/// 1. `[]` Owner account (the PDA derived from `["owner"]`)
+	/// 2. `[]` Program Config account
+	/// 3. `[]` This program
+	fn mock_caller_process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+		let (owner_pda, bump) = Pubkey::find_program_address(&[OWNER_SEED], program_id);
+		let instruction = Instruction {
+			program_id: *accounts[3].key,
+			accounts: vec![
+				AccountMeta::new(*accounts[0].key, false),
+				AccountMeta::new_readonly(owner_pda, true),
+				AccountMeta::new_readonly(*accounts[2].key, false),
+			],
+			data: data[1..].to_vec(),
+		};
+
+		let bump = [bump];
+		let owner_seeds: &[&[u8]] = &[OWNER_SEED, &bump];
+		let signers_seeds: &[&[&[u8]]] = if data[0] == 1 { &[owner_seeds] } else { &[] };
+		invoke_signed(&instruction, accounts, signers_seeds)
+	}
+
+	#[test]
+	fn test_initialize_via_cpi_matches_direct_call() {
+		let mut driver = ProgramDriver::new();
+		let program_id = driver.program_id;
+		driver.add(TestAccount { key: program_id, ..TestAccount::new(Pubkey::default(), 1, vec![]) });
+
+		let (owner_pda, _) = Pubkey::find_program_address(&[OWNER_SEED], &mock_caller_id());
+		driver.add(TestAccount { key: owner_pda, ..TestAccount::new(system_program::id(), 0, vec![]) });
+		let tenant = driver.add_wallet(0);
+		let escrow = driver.add_program_account(SecurityEscrowAccount::LEN);
+
+		// Directly, with the owner signing
+		let direct = Agreement { agreement: driver.add_program_account(RentAgreementAccount::LEN), owner: owner_pda, tenant, escrow };
+		initialize(&mut driver, &direct, &init_params(&direct)).unwrap();
+
+		// Via CPI from the parent program, signing for its PDA owner with `invoke_signed`
+		let agreement = driver.add_program_account(RentAgreementAccount::LEN);
+		let config = driver.config_pda();
+		let cpi = |sign: bool| {
+			let mut data = vec![sign as u8];
+			data.extend_from_slice(&initialize_data(&init_params(&direct)));
+			let accounts = vec![
+				AccountMeta::new(agreement, false),
+				AccountMeta::new_readonly(owner_pda, false),
+				AccountMeta::new_readonly(config, false),
+				AccountMeta::new_readonly(program_id, false),
+			];
+			(accounts, data)
+		};
+
+		let (accounts, data) = cpi(false);
+		assert_eq!(driver.process_program(mock_caller_id(), mock_caller_process, accounts, data), Err(ProgramError::MissingRequiredSignature));
+		assert!(driver.account(&agreement).data.iter().all(|byte| *byte == 0));
+
+		let (accounts, data) = cpi(true);
+		driver.process_program(mock_caller_id(), mock_caller_process, accounts, data).unwrap();
+
+		let state = driver.agreement(&agreement);
+		assert_eq!(state.owner_pubkey, owner_pda);
+		assert_eq!(state.status, AgreementStatus::DepositPending as u8);
+		assert_eq!(state, driver.agreement(&direct.agreement));
+	}
+
+	fn top_up(driver: &mut ProgramDriver, agreement: &Agreement, amount: u64) -> ProgramResult {
+		let mut data = vec![16];
+		data.extend_from_slice(&amount.to_le_bytes());
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new(agreement.escrow, false),
+				AccountMeta::new_readonly(system_program::id(), false),
+			],
+			data,
+		)
+	}
+
+	#[test]
+	fn test_top_up_escrow() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		let escrow_lamports = driver.lamports(&agreement.escrow);
+
+		top_up(&mut driver, &agreement, 500).unwrap();
+		let escrow = driver.escrow(&agreement.escrow);
+		assert_eq!(escrow.remaining_deposit, Lamports(DEPOSIT + 500));
+		assert_eq!(escrow.total_deposited, Lamports(DEPOSIT + 500));
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_lamports + 500);
+
+		// Not once the agreement is completed
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+		assert_eq!(top_up(&mut driver, &agreement, 500), Err(TrustedPropertiesError::AgreementFinalized.into()));
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT + 500));
+	}
+
+	#[test]
+	fn test_deposit_rejects_escrow_of_another_agreement() {
+		let mut driver = ProgramDriver::new();
+		let first = active_agreement(&mut driver);
+
+		// A second agreement of the same tenant naming the escrow holding the first agreement's deposit
+		let second = Agreement { agreement: driver.add_program_account(RentAgreementAccount::LEN), ..first };
+		initialize(&mut driver, &second, &init_params(&second)).unwrap();
+		let tenant_lamports = driver.lamports(&second.tenant);
+
+		assert_eq!(deposit(&mut driver, &second), Err(ProgramError::InvalidAccountData));
+		assert_eq!(driver.lamports(&second.tenant), tenant_lamports);
+		assert_eq!(driver.escrow(&first.escrow).remaining_deposit, Lamports(DEPOSIT));
+		assert_eq!(driver.escrow(&first.escrow).agreement_pubkey, first.agreement);
+		assert_eq!(driver.agreement(&second.agreement).status, AgreementStatus::DepositPending as u8);
+	}
+
+	#[test]
+	fn test_refund_rejects_escrow_of_another_agreement() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+
+		// The escrow named by the agreement, but bound to another one
+		let mut escrow = driver.escrow(&agreement.escrow);
+		escrow.agreement_pubkey = Pubkey::new_unique();
+		driver.account_mut(&agreement.escrow).data = escrow.try_to_vec().unwrap();
+
+		assert_eq!(refund(&mut driver, &agreement), Err(ProgramError::InvalidAccountData));
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT));
+	}
+
+	const PLATFORM_FEE_BPS: u16 = 1_000;
+	const LATE_FEE: u64 = 100_000;
+
+	#[test]
+	fn test_pay_rent_platform_fee_on_rent_only() {
+		let mut driver = ProgramDriver::new();
+		let platform_wallet = driver.add_wallet(0);
+		// Started last month: the first payment is overdue
+		let agreement = active_agreement_with(&mut driver, |params| {
+			params.start_month = 12;
+			params.start_year = 2021;
+			params.late_fee = LATE_FEE;
+			params.platform_fee_bps = PLATFORM_FEE_BPS;
+			params.platform_wallet = platform_wallet;
+		});
+
+		assert_eq!(pay_rent_with(&mut driver, &agreement, RENT + LATE_FEE, 1, None), Err(ProgramError::InvalidAccountData));
+
+		pay_rent_with(&mut driver, &agreement, RENT + LATE_FEE, 1, Some(platform_wallet)).unwrap();
+		assert_eq!(driver.lamports(&platform_wallet), RENT / 10);
+		assert_eq!(driver.lamports(&agreement.owner), RENT - RENT / 10 + LATE_FEE);
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 1);
+	}
+
+	#[test]
+	fn test_pay_rent_split_platform_fee() {
+		let mut driver = ProgramDriver::new();
+		let platform_wallet = driver.add_wallet(0);
+		let co_owner = driver.add_wallet(0);
+		let agreement = active_agreement_with(&mut driver, |params| {
+			params.platform_fee_bps = PLATFORM_FEE_BPS;
+			params.platform_wallet = platform_wallet;
+		});
+
+		let mut data = vec![10, 1];
+		data.extend_from_slice(co_owner.as_ref());
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+			],
+			data,
+		).unwrap();
+
+		let pay_rent_split = |driver: &mut ProgramDriver, platform_wallet: Option<Pubkey>| {
+			let mut data = vec![28, 2];
+			for (payee, share_bps) in [(agreement.owner, 7_500u16), (co_owner, 2_500)] {
+				data.extend_from_slice(payee.as_ref());
+				data.extend_from_slice(&share_bps.to_le_bytes());
+			}
+			let mut accounts = vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new_readonly(system_program::id(), false),
+				AccountMeta::new(agreement.owner, false),
+				AccountMeta::new(co_owner, false),
+			];
+			accounts.extend(platform_wallet.map(|wallet| AccountMeta::new(wallet, false)));
+			driver.process(accounts, data)
+		};
+
+		assert_eq!(pay_rent_split(&mut driver, None), Err(ProgramError::InvalidAccountData));
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 0);
+
+		// The payees split the rent left after the platform fee
+		pay_rent_split(&mut driver, Some(platform_wallet)).unwrap();
+		let payees_share = RENT - RENT / 10;
+		assert_eq!(driver.lamports(&platform_wallet), RENT / 10);
+		assert_eq!(driver.lamports(&agreement.owner), payees_share * 3 / 4);
+		assert_eq!(driver.lamports(&co_owner), payees_share / 4);
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 1);
+	}
+
+	#[test]
+	fn test_crank_auto_pay_platform_fee() {
+		let mut driver = ProgramDriver::new();
+		let platform_wallet = driver.add_wallet(0);
+		let cranker = driver.add_wallet(0);
+		let agreement = active_agreement_with(&mut driver, |params| {
+			params.platform_fee_bps = PLATFORM_FEE_BPS;
+			params.platform_wallet = platform_wallet;
+		});
+
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new_readonly(cranker, false),
+				AccountMeta::new(agreement.escrow, false),
+				AccountMeta::new_readonly(system_program::id(), false),
+			],
+			vec![27, 2],
+		).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).prepaid_rent, 2 * RENT);
+
+		let crank = |driver: &mut ProgramDriver, platform_wallet: Option<Pubkey>| {
+			let mut accounts = vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(cranker, true),
+				AccountMeta::new(agreement.owner, false),
+				AccountMeta::new(agreement.tenant, false),
+				AccountMeta::new(agreement.escrow, false),
+			];
+			accounts.extend(platform_wallet.map(|wallet| AccountMeta::new(wallet, false)));
+			driver.process(accounts, vec![9])
+		};
+
+		assert_eq!(crank(&mut driver, None), Err(ProgramError::InvalidAccountData));
+
+		crank(&mut driver, Some(platform_wallet)).unwrap();
+		assert_eq!(driver.lamports(&platform_wallet), RENT / 10);
+		assert_eq!(driver.lamports(&agreement.owner), RENT - RENT / 10);
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.payments_made, 1);
+		assert_eq!(rent_data.prepaid_rent, RENT);
+	}
+
+	#[test]
+	fn test_pay_rent_nonces() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+
+		assert_eq!(pay_rent(&mut driver, &agreement, 0), Err(TrustedPropertiesError::InvalidInstructionParameter.into()));
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 0);
+
+		pay_rent(&mut driver, &agreement, 5).unwrap();
+		let owner_lamports = driver.lamports(&agreement.owner);
+
+		// A replay of the last nonce succeeds without paying again; an older nonce is rejected
+		pay_rent(&mut driver, &agreement, 5).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), owner_lamports);
+		assert_eq!(pay_rent(&mut driver, &agreement, 4), Err(TrustedPropertiesError::InvalidInstructionParameter.into()));
+
+		pay_rent(&mut driver, &agreement, 6).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.payments_made, 2);
+		assert_eq!(rent_data.last_op_nonce, 6);
+		assert_eq!(driver.lamports(&agreement.owner), owner_lamports + RENT);
+	}
+
+	#[test]
+	fn test_completion_requires_escrow() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+
+		let mut data = vec![2];
+		data.extend_from_slice(&RENT.to_le_bytes());
+		data.extend_from_slice(&2u64.to_le_bytes());
+		let last_payment = vec![
+			AccountMeta::new(agreement.agreement, false),
+			AccountMeta::new(agreement.tenant, true),
+			AccountMeta::new(agreement.owner, false),
+			AccountMeta::new_readonly(system_program::id(), false),
+		];
+		assert_eq!(driver.process(last_payment, data), Err(ProgramError::NotEnoughAccountKeys));
+		assert_eq!(driver.agreement(&agreement.agreement).remaining_payments, 1);
+
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Completed as u8);
+	}
+
+	#[test]
+	fn test_waive_rent_completion_requires_escrow() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+
+		let waive_rent = |driver: &mut ProgramDriver, months: u8, escrow: Option<Pubkey>| {
+			let mut accounts = vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+			];
+			accounts.extend(escrow.map(|escrow| AccountMeta::new_readonly(escrow, false)));
+			driver.process(accounts, vec![29, months])
+		};
+
+		// Waiving without completing the agreement needs no escrow
+		waive_rent(&mut driver, 1, None).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).remaining_payments, 1);
+
+		assert_eq!(waive_rent(&mut driver, 1, None), Err(ProgramError::NotEnoughAccountKeys));
+		assert_eq!(driver.agreement(&agreement.agreement).remaining_payments, 1);
+
+		waive_rent(&mut driver, 1, Some(agreement.escrow)).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.status, AgreementStatus::Completed as u8);
+		assert_eq!(rent_data.waived_payments, 2);
+	}
+
+	#[test]
+	fn test_record_rent_payment_counters_overflow() {
+		let overflow: ProgramResult = Err(TrustedPropertiesError::ArithmeticOverflow.into());
+		let active = RentAgreementAccount {
+			status: AgreementStatus::Active as u8,
+			duration: 255,
+			remaining_payments: 1,
+			start_month: 1,
+			start_year: 2022,
+			..Default::default()
+		};
+
+		let mut rent_data = RentAgreementAccount { payments_made: u8::MAX, ..active };
+		assert_eq!(Processor::record_rent_payment(&mut rent_data, u8::MAX), overflow);
+
+		let mut rent_data = RentAgreementAccount { remaining_payments: 0, payments_made: 3, ..active };
+		assert_eq!(Processor::record_rent_payment(&mut rent_data, 4), overflow);
+
+		let mut rent_data = RentAgreementAccount { payments_made: 254, ..active };
+		assert_eq!(Processor::record_rent_payment(&mut rent_data, 255), Ok(()));
+		assert_eq!((rent_data.payments_made, rent_data.remaining_payments), (255, 0));
+		assert_eq!(rent_data.status, AgreementStatus::Completed as u8);
+	}
+
+	fn adjust_deposit(driver: &mut ProgramDriver, agreement: &Agreement, new_deposit: u64) -> ProgramResult {
+		let mut data = vec![30];
+		data.extend_from_slice(&new_deposit.to_le_bytes());
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new(agreement.escrow, false),
+				AccountMeta::new_readonly(system_program::id(), false),
+			],
+			data,
+		)
+	}
+
+	/// Set the escrow's total ever deposited, eg: close to its limit
+	fn set_total_deposited(driver: &mut ProgramDriver, agreement: &Agreement, total_deposited: u64) {
+		let mut escrow = driver.escrow(&agreement.escrow);
+		escrow.total_deposited = Lamports(total_deposited);
+		driver.account_mut(&agreement.escrow).data = escrow.try_to_vec().unwrap();
+	}
+
+	#[test]
+	fn test_total_deposited_overflow() {
+		let overflow: ProgramResult = Err(TrustedPropertiesError::ArithmeticOverflow.into());
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		set_total_deposited(&mut driver, &agreement, u64::MAX - 100);
+
+		assert_eq!(top_up(&mut driver, &agreement, 500), overflow);
+		assert_eq!(adjust_deposit(&mut driver, &agreement, DEPOSIT + 500), overflow);
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT));
+
+		top_up(&mut driver, &agreement, 100).unwrap();
+		assert_eq!(driver.escrow(&agreement.escrow).total_deposited, Lamports(u64::MAX));
+	}
+
+	fn terminate_early(driver: &mut ProgramDriver, agreement: &Agreement, party: Pubkey, is_signer: bool) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(party, is_signer),
+			],
+			vec![3],
+		)
+	}
+
+	#[test]
+	fn test_terminate_early_requires_party_signer() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		let stranger = driver.add_wallet(0);
+
+		assert_eq!(terminate_early(&mut driver, &agreement, agreement.tenant, false), Err(ProgramError::MissingRequiredSignature));
+		assert_eq!(terminate_early(&mut driver, &agreement, stranger, true), Err(TrustedPropertiesError::TenantMismatch.into()));
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Active as u8);
+
+		terminate_early(&mut driver, &agreement, agreement.tenant, true).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.status, AgreementStatus::Terminated as u8);
+		assert_eq!(rent_data.remaining_payments, 0);
+
+		// By the owner
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		terminate_early(&mut driver, &agreement, agreement.owner, true).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Terminated as u8);
+	}
+
+	/// An agreement initialized, deposited & paid in full: completed, its deposit still held
+	fn completed_agreement(driver: &mut ProgramDriver) -> Agreement {
+		let agreement = active_agreement(driver);
+		pay_rent(driver, &agreement, 1).unwrap();
+		pay_rent(driver, &agreement, 2).unwrap();
+		agreement
+	}
+
+	/// Renew the agreement by both parties, for a term starting in March 2022
+	fn renew(driver: &mut ProgramDriver, agreement: &Agreement, duration: u8, rent_amount: u64) -> ProgramResult {
+		let mut data = vec![40, duration];
+		data.extend_from_slice(&rent_amount.to_le_bytes());
+		data.push(3);
+		data.extend_from_slice(&2022u16.to_le_bytes());
+		let config = driver.config_pda();
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+				AccountMeta::new_readonly(agreement.tenant, true),
+				AccountMeta::new_readonly(agreement.escrow, false),
+				AccountMeta::new_readonly(config, false),
+			],
+			data,
+		)
+	}
+
+	/// Store `config` in the program config PDA, as `SetConfig` would
+	fn set_config(driver: &mut ProgramDriver, config: ProgramConfig) {
+		let program_id = driver.program_id;
+		let config_pda = driver.config_pda();
+		let account = driver.account_mut(&config_pda);
+		account.owner = program_id;
+		account.data = config.try_to_vec().unwrap();
+	}
+
+	#[test]
+	fn test_renew_agreement_resets_the_term() {
+		let mut driver = ProgramDriver::new();
+		let agreement = completed_agreement(&mut driver);
+
+		let mut rent_data = driver.agreement(&agreement.agreement);
+		rent_data.movein_report_hash = [1; 32];
+		rent_data.moveout_report_hash = [2; 32];
+		rent_data.occupancy_breach = true;
+		pack_into(&rent_data, &mut driver.account_mut(&agreement.agreement).data).unwrap();
+
+		renew(&mut driver, &agreement, 3, 2 * RENT).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.status, AgreementStatus::Active as u8);
+		assert_eq!((rent_data.duration, rent_data.remaining_payments, rent_data.payments_made), (3, 3, 0));
+		assert_eq!((rent_data.rent_amount, rent_data.start_month, rent_data.start_year), (2 * RENT, 3, 2022));
+		assert_eq!((rent_data.movein_report_hash, rent_data.moveout_report_hash), ([0; 32], [0; 32]));
+		assert!(!rent_data.occupancy_breach);
+		assert_eq!(rent_data.last_op_nonce, 0);
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT));
+
+		// The nonces of the new term start over
+		pay_rent_with(&mut driver, &agreement, 2 * RENT, 1, None).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 1);
+	}
+
+	#[test]
+	fn test_renew_agreement_validates_the_terms() {
+		let invalid: ProgramResult = Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		let mut driver = ProgramDriver::new();
+		let agreement = completed_agreement(&mut driver);
+		set_config(&mut driver, ProgramConfig { max_duration: 12, min_deposit_months: 1, ..ProgramConfig::default() });
+
+		assert_eq!(renew(&mut driver, &agreement, 3, 0), invalid);
+		assert_eq!(renew(&mut driver, &agreement, 13, RENT), invalid);
+		// The held deposit covers less than a month of the renewed rent
+		assert_eq!(renew(&mut driver, &agreement, 3, DEPOSIT + 1), invalid);
+
+		set_config(&mut driver, ProgramConfig { paused: true, ..ProgramConfig::default() });
+		assert_eq!(renew(&mut driver, &agreement, 3, RENT), Err(TrustedPropertiesError::ProgramPaused.into()));
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Completed as u8);
+
+		set_config(&mut driver, ProgramConfig { max_duration: 12, min_deposit_months: 1, ..ProgramConfig::default() });
+		renew(&mut driver, &agreement, 12, DEPOSIT).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).duration, 12);
+	}
+
+	/// Process an instruction taking the agreement & the signing `party` (eg: the owner or the tenant)
+	fn by_party(driver: &mut ProgramDriver, agreement: &Agreement, party: Pubkey, data: Vec<u8>) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(party, true),
+			],
+			data,
+		)
+	}
+
+	/// Process an instruction taking the agreement, signed by both the owner & the tenant
+	fn by_both_parties(driver: &mut ProgramDriver, agreement: &Agreement, data: Vec<u8>) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+				AccountMeta::new_readonly(agreement.tenant, true),
+			],
+			data,
+		)
+	}
+
+	#[test]
+	fn test_dispute_blocks_payments_until_resolved() {
+		let invalid_status: ProgramResult = Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+
+		by_party(&mut driver, &agreement, agreement.tenant, vec![17]).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.status, AgreementStatus::Disputed as u8);
+		assert_eq!(rent_data.disputed_by, agreement.tenant);
 
-		let instruction = TrustedPropertiesInstruction::unpack(instruction_data)?;
-		match instruction {
-			// Initialize the rent-contract
-			TrustedPropertiesInstruction::InitializeRentContract {
-				owner_pubkey,
-				tenant_pubkey,
-				security_escrow_pubkey,
-				security_deposit,
-				rent_amount,
-				duration,
-				start_month,
-				start_year,
-			} => Self::initialize_rent_contract(accounts, program_id, owner_pubkey, tenant_pubkey, security_escrow_pubkey, security_deposit, rent_amount, duration, start_month, start_year),
+		assert_eq!(pay_rent(&mut driver, &agreement, 1), invalid_status);
+		assert_eq!(top_up(&mut driver, &agreement, 500), invalid_status);
+		assert_eq!(terminate_early(&mut driver, &agreement, agreement.owner, true), invalid_status);
+		assert_eq!(by_party(&mut driver, &agreement, agreement.tenant, vec![4, 1]), invalid_status);
+		assert_eq!(driver.lamports(&agreement.owner), 0);
 
-			// Pay first-time security_deposit amount (from tenant to escrow) & confirm the agreement
-			TrustedPropertiesInstruction::DepositSecurity { security_deposit_amount } => Self::deposit_security(accounts, program_id, security_deposit_amount),
+		// Both parties must sign the resolution
+		let owner_only = vec![
+			AccountMeta::new(agreement.agreement, false),
+			AccountMeta::new_readonly(agreement.owner, true),
+			AccountMeta::new_readonly(agreement.tenant, false),
+		];
+		assert_eq!(driver.process(owner_only, vec![18]), Err(ProgramError::MissingRequiredSignature));
 
-			// Pay rent from (tenant to owner)
-			TrustedPropertiesInstruction::PayRent { rent_amount } => Self::pay_rent(accounts, program_id, rent_amount),
+		by_both_parties(&mut driver, &agreement, vec![18]).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Active as u8);
 
-			// Terminate the contract early
-			TrustedPropertiesInstruction::TerminateEarly {} => Self::terminate_early(accounts, program_id),
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), RENT);
+	}
 
-			// Request to extend the contract duration (by Tenant)
-			TrustedPropertiesInstruction::RequestContractDurationExtension { extension_duration } => Self::extend_contract_duration_request(accounts, program_id, extension_duration),
+	fn verify_escrow_solvency(driver: &mut ProgramDriver, agreement: &Agreement) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new_readonly(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.escrow, false),
+				AccountMeta::new_readonly(sysvar::rent::id(), false),
+			],
+			vec![19],
+		)
+	}
 
-			// Confirm to extend the contract duration (by Owner)
-			TrustedPropertiesInstruction::ConfirmContractDurationExtension { extension_duration } => Self::extend_contract_duration_confirm(accounts, program_id, extension_duration),
+	#[test]
+	fn test_verify_escrow_solvency() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		verify_escrow_solvency(&mut driver, &agreement).unwrap();
+
+		// Lamports drained from the escrow behind the program's back
+		driver.account_mut(&agreement.escrow).lamports -= 1;
+		assert_eq!(verify_escrow_solvency(&mut driver, &agreement), Err(TrustedPropertiesError::EscrowInsolvent.into()));
+	}
+
+	#[test]
+	fn test_pay_rent_escalated_after_a_year() {
+		let mut driver = ProgramDriver::new();
+		// Started 11 months ago: the payments 1 to 12 are due
+		let agreement = active_agreement_with(&mut driver, |params| {
+			params.duration = 13;
+			params.start_month = 2;
+			params.start_year = 2021;
+			params.annual_increase_bps = 500;
+		});
+		driver.account_mut(&agreement.tenant).lamports = 20 * RENT;
+
+		for nonce in 1..=12 {
+			pay_rent(&mut driver, &agreement, nonce).unwrap();
 		}
+		assert_eq!(driver.lamports(&agreement.owner), 12 * RENT);
+
+		// The 13th payment is in the second year: escalated by 5%
+		assert_eq!(pay_rent(&mut driver, &agreement, 13), Err(TrustedPropertiesError::IncorrectPaymentAmount.into()));
+		pay_rent_with(&mut driver, &agreement, RENT + RENT / 20, 13, None).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), 13 * RENT + RENT / 20);
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Completed as u8);
 	}
 
+	#[test]
+	fn test_initialize_and_deposit() {
+		let mut driver = ProgramDriver::new();
+		let agreement = setup(&mut driver);
+		let escrow_lamports = driver.lamports(&agreement.escrow);
+		let config = driver.config_pda();
 
-	/// Initialize the Contract Account for the rent agreement
-	fn initialize_rent_contract(
-		accounts: &[AccountInfo],
-		program_id: &Pubkey,
-		owner_pubkey: Pubkey,
-		tenant_pubkey: Pubkey,
-		security_escrow_pubkey: Pubkey,
-		security_deposit: u64,
-		rent_amount: u64,
-		duration: u8,
-		start_month: u8,
-		start_year: u16,
-	) -> ProgramResult {
+		let initialize_and_deposit = |driver: &mut ProgramDriver, tenant_signs: bool| {
+			let mut data = initialize_data(&init_params(&agreement));
+			data[0] = 20;
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new_readonly(agreement.owner, true),
+					AccountMeta::new_readonly(sysvar::rent::id(), false),
+					AccountMeta::new(agreement.tenant, tenant_signs),
+					AccountMeta::new(agreement.escrow, false),
+					AccountMeta::new_readonly(system_program::id(), false),
+					AccountMeta::new_readonly(config, false),
+				],
+				data,
+			)
+		};
 
-		let accounts_iter = &mut accounts.iter();
+		// Without the tenant's signature, not even the initialization is kept
+		assert_eq!(initialize_and_deposit(&mut driver, false), Err(ProgramError::MissingRequiredSignature));
+		assert!(driver.account(&agreement.agreement).data.iter().all(|byte| *byte == 0));
 
-		let rent_agreement_account = next_account_info(accounts_iter)?;
-		if rent_agreement_account.owner != program_id {
-			msg!("[TrustedProperties] ERROR: Rent Agreement account must be owned by this program");
-			return Err(ProgramError::IncorrectProgramId);
-		}
+		initialize_and_deposit(&mut driver, true).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Active as u8);
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT));
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_lamports + DEPOSIT);
+		assert_eq!(driver.lamports(&agreement.tenant), 10 * RENT - DEPOSIT);
+	}
 
-		let solana_rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
-		// Make sure this account is rent exempt
-		// Program owners can maintain a minimum amount of Lamports to keep the program rent-free.
-		if !solana_rent.is_exempt(
-			rent_agreement_account.lamports(),
-			rent_agreement_account.data_len(),
-		) {
-			msg!("[TrustedProperties] ERROR: Rent Agreement account not rent exempt. Balance: {}", rent_agreement_account.lamports());
-			return Err(ProgramError::AccountNotRentExempt);
-		}
+	fn terminate_with_proration(driver: &mut ProgramDriver, agreement: &Agreement, days_occupied: u8) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new(agreement.owner, false),
+				AccountMeta::new(agreement.escrow, false),
+			],
+			vec![22, days_occupied],
+		)
+	}
 
-		// Initialize the Rent Agreement Account with the initial data
-		// Note: the structure of the data state must match the `space` reserved when account created
-		let rent_agreement_data = RentAgreementAccount::try_from_slice(&rent_agreement_account.data.borrow());
+	#[test]
+	fn test_terminate_with_proration() {
+		for (days_occupied, prorated_rent) in [(15, RENT / 2), (30, RENT)] {
+			let mut driver = ProgramDriver::new();
+			let agreement = active_agreement(&mut driver);
+			let escrow_lamports = driver.lamports(&agreement.escrow);
 
-		if rent_agreement_data.is_err() {
-			msg!("[TrustedProperties] ERROR: Rent Agreement account data size is incorrect: {}", rent_agreement_account.try_data_len()?);
-			return Err(ProgramError::InvalidAccountData);
+			terminate_with_proration(&mut driver, &agreement, days_occupied).unwrap();
+			assert_eq!(driver.lamports(&agreement.owner), prorated_rent);
+			assert_eq!(driver.lamports(&agreement.tenant), 10 * RENT - prorated_rent);
+			assert_eq!(driver.lamports(&agreement.escrow), escrow_lamports - DEPOSIT);
+			let escrow = driver.escrow(&agreement.escrow);
+			assert_eq!((escrow.remaining_deposit, escrow.penalties_applied), (Lamports(0), Lamports(prorated_rent)));
+			let rent_data = driver.agreement(&agreement.agreement);
+			assert_eq!((rent_data.status, rent_data.owed_to_owner), (AgreementStatus::Terminated as u8, 0));
 		}
 
-		let mut rent_data = rent_agreement_data.unwrap();
-		if rent_data.is_initialized() {
-			msg!("[TrustedProperties] ERROR: Rent Agreement account already initialized");
-			return Err(ProgramError::AccountAlreadyInitialized);
-		}
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		assert_eq!(terminate_with_proration(&mut driver, &agreement, 31), Err(TrustedPropertiesError::InvalidInstructionParameter.into()));
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Active as u8);
+	}
 
-		rent_data.status = AgreementStatus::DepositPending as u8;
-		rent_data.owner_pubkey = owner_pubkey;
-		rent_data.tenant_pubkey = tenant_pubkey;
-		rent_data.security_escrow_pubkey = security_escrow_pubkey;
-		rent_data.security_deposit = security_deposit;
-		rent_data.rent_amount = rent_amount;
-		rent_data.duration = duration;
-		rent_data.remaining_payments = duration;
-		rent_data.start_month = start_month;
-		rent_data.start_year = start_year;
-		rent_data.duration_extension_request = 0;
-		rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+	#[test]
+	fn test_refund_locked_until_delay() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement_with(&mut driver, |params| params.refund_delay_days = 10);
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+		let completed_ts = driver.agreement(&agreement.agreement).completed_ts;
 
-		msg!("[TrustedProperties] Rent Agreement account initialized successfully: {:?}", rent_data);
+		set_now(completed_ts + 10 * 86_400 - 1);
+		assert_eq!(refund(&mut driver, &agreement), Err(TrustedPropertiesError::RefundLocked.into()));
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT));
 
-		Ok(())
+		set_now(completed_ts + 10 * 86_400);
+		refund(&mut driver, &agreement).unwrap();
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(0));
+		assert_eq!(driver.lamports(&agreement.tenant), 8 * RENT);
 	}
 
+	#[test]
+	fn test_migrate_escrow() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		let escrow_rent_exempt = Rent::default().minimum_balance(SecurityEscrowAccount::LEN);
+		driver.account_mut(&agreement.owner).lamports = escrow_rent_exempt;
+		let old_escrow_lamports = driver.lamports(&agreement.escrow);
 
-	/// Pay the rent (tenant -> owner)
-	fn pay_rent(accounts: &[AccountInfo], program_id: &Pubkey, rent_amount: u64) -> ProgramResult {
+		let (new_escrow, _) = Pubkey::find_program_address(&[ESCROW_SEED, agreement.agreement.as_ref()], &driver.program_id);
+		driver.add(TestAccount { key: new_escrow, ..TestAccount::new(system_program::id(), 0, vec![0; SecurityEscrowAccount::LEN]) });
 
-		let accounts_iter = &mut accounts.iter();
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.owner, true),
+				AccountMeta::new_readonly(agreement.tenant, true),
+				AccountMeta::new(agreement.escrow, false),
+				AccountMeta::new(new_escrow, false),
+				AccountMeta::new_readonly(system_program::id(), false),
+			],
+			vec![24],
+		).unwrap();
 
-		let rent_agreement_account = next_account_info(accounts_iter)?;
-		if rent_agreement_account.owner != program_id {
-			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
-			return Err(ProgramError::IncorrectProgramId);
-		}
+		assert_eq!(driver.agreement(&agreement.agreement).security_escrow_pubkey, new_escrow);
+		assert_eq!(driver.account(&new_escrow).owner, driver.program_id);
+		assert_eq!(driver.lamports(&new_escrow), escrow_rent_exempt + DEPOSIT);
+		assert_eq!(driver.lamports(&agreement.escrow), old_escrow_lamports - DEPOSIT);
+		let escrow = driver.escrow(&new_escrow);
+		assert_eq!((escrow.agreement_pubkey, escrow.remaining_deposit), (agreement.agreement, Lamports(DEPOSIT)));
+		let old_escrow = driver.escrow(&agreement.escrow);
+		assert!(old_escrow.closed);
+		assert_eq!(old_escrow.remaining_deposit, Lamports(0));
 
-		let tenant_account = next_account_info(accounts_iter)?;
-		let owner_account: &AccountInfo = next_account_info(accounts_iter)?;
-		let system_program_account = next_account_info(accounts_iter)?;
+		// The deposit is refunded from the new escrow
+		let agreement = Agreement { escrow: new_escrow, ..agreement };
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+		refund(&mut driver, &agreement).unwrap();
+		assert_eq!(driver.lamports(&new_escrow), escrow_rent_exempt);
+	}
 
-		if !tenant_account.is_signer {
-			return Err(ProgramError::MissingRequiredSignature);
-		}
+	/// Add an SPL token account of the `mint`, belonging to `owner` & holding `amount` tokens
+	fn add_token_account(driver: &mut ProgramDriver, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+		let token_account = TokenAccount { mint, owner, amount, state: spl_token::state::AccountState::Initialized, ..TokenAccount::default() };
+		let mut data = vec![0; TokenAccount::LEN];
+		TokenAccount::pack(token_account, &mut data).unwrap();
+		driver.add(TestAccount::new(spl_token::id(), Rent::default().minimum_balance(TokenAccount::LEN), data))
+	}
 
-		if tenant_account.lamports() < rent_amount {
-			return Err(ProgramError::InsufficientFunds);
-		}
+	fn token_amount(driver: &ProgramDriver, token_account: &Pubkey) -> u64 {
+		TokenAccount::unpack(&driver.account(token_account).data).unwrap().amount
+	}
 
-		// Transfer to self? Do nothing.
-		if tenant_account.key == owner_account.key {
-			return Ok(());
-		}
+	#[test]
+	fn test_pay_rent_token() {
+		let mut driver = ProgramDriver::new();
+		driver.add(TestAccount { key: spl_token::id(), ..TestAccount::new(Pubkey::default(), 1, vec![]) });
+		let mint = Pubkey::new_unique();
+		let agreement = active_agreement_with(&mut driver, |params| params.payment_mint = mint);
+		let tenant_tokens = add_token_account(&mut driver, mint, agreement.tenant, 2 * RENT);
+		let owner_tokens = add_token_account(&mut driver, mint, agreement.owner, 0);
+		let other_mint_tokens = add_token_account(&mut driver, Pubkey::new_unique(), agreement.owner, 0);
 
-		// Initialize the Rent Agreement Account with the initial data
-		// Note: the structure of the data state must match the `space` the client used to create the account
-		let rent_agreement_data = RentAgreementAccount::try_from_slice(&rent_agreement_account.data.borrow());
+		let pay_rent_token = |driver: &mut ProgramDriver, owner_tokens: Pubkey| {
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new_readonly(agreement.tenant, true),
+					AccountMeta::new(tenant_tokens, false),
+					AccountMeta::new(owner_tokens, false),
+					AccountMeta::new_readonly(spl_token::id(), false),
+					AccountMeta::new_readonly(agreement.escrow, false),
+				],
+				vec![7],
+			)
+		};
 
-		if rent_agreement_data.is_err() {
-			msg!("[TrustedProperties] Rent agreement account data size incorrect: {}", rent_agreement_account.try_data_len()?);
-			return Err(ProgramError::InvalidAccountData);
-		}
+		assert_eq!(pay_rent_token(&mut driver, other_mint_tokens), Err(ProgramError::InvalidAccountData));
 
-		let mut rent_data = rent_agreement_data.unwrap();
-		if !rent_data.is_initialized() {
-			msg!("[TrustedProperties] ERROR: Invalid agreement: Rent agreement account not initialized.");
-			return Err(ProgramError::UninitializedAccount);
-		}
+		pay_rent_token(&mut driver, owner_tokens).unwrap();
+		assert_eq!((token_amount(&driver, &tenant_tokens), token_amount(&driver, &owner_tokens)), (RENT, RENT));
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 1);
+		assert_eq!(driver.lamports(&agreement.owner), 0);
 
-		// Make sure we pay the same account used during the agreement initialization
-		if rent_data.owner_pubkey != *owner_account.key {
-			msg!("[TrustedProperties] ERROR: Owner's public-key (owner_pubkey) does not match the one used during agreement initialization");
-			return Err(ProgramError::InvalidAccountData);
-		}
+		pay_rent_token(&mut driver, owner_tokens).unwrap();
+		assert_eq!(token_amount(&driver, &owner_tokens), 2 * RENT);
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Completed as u8);
 
-		msg!("[TrustedProperties] Transferring {} lamports from tenant (current balance: {})", rent_amount, tenant_account.lamports());
+		// Paid in tokens, never in SOL
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement_with(&mut driver, |params| params.payment_mint = mint);
+		assert_eq!(pay_rent(&mut driver, &agreement, 1), Err(TrustedPropertiesError::InvalidInstruction.into()));
+	}
 
-		if rent_data.is_completed() {
-			msg!("[TrustedProperties] ERROR: Rent already paid in full");
-			return Err(TrustedPropertiesError::RentAlreadyFullyPaid.into());
-		}
+	/// Pause or resume the program, with an existing config (the ProgramData account is only read to create it)
+	fn set_paused(driver: &mut ProgramDriver, authority: Pubkey, paused: bool) -> ProgramResult {
+		let config = driver.config_pda();
+		driver.process(
+			vec![
+				AccountMeta::new(config, false),
+				AccountMeta::new(authority, true),
+				AccountMeta::new_readonly(system_program::id(), false),
+				AccountMeta::new_readonly(system_program::id(), false),
+			],
+			vec![25, paused as u8],
+		)
+	}
 
-		if rent_data.is_terminated() {
-			msg!("[TrustedProperties] ERROR: Rent agreement already terminated");
-			return Err(TrustedPropertiesError::RentAgreementTerminated.into());
-		}
+	#[test]
+	fn test_pause_blocks_new_agreements_only() {
+		let mut driver = ProgramDriver::new();
+		let authority = driver.add_wallet(0);
+		let stranger = driver.add_wallet(0);
+		set_config(&mut driver, ProgramConfig { authority, ..ProgramConfig::default() });
+		let existing = active_agreement(&mut driver);
 
-		// TODO: Allow advance payment (transfer amount more than the monthly rent amount). This can go into the escrow account as advance deposit.
-		if rent_data.rent_amount != rent_amount {
-			msg!("[TrustedProperties] ERROR: Rent amount ({}) does not match the agreement amount ({})", rent_amount, rent_data.rent_amount);
-			return Err(TrustedPropertiesError::IncorrectPaymentAmount.into());
-		}
+		assert_eq!(set_paused(&mut driver, stranger, true), Err(ProgramError::InvalidAccountData));
+		set_paused(&mut driver, authority, true).unwrap();
 
-		// Create instruction to transfer the rent-amount (lamports) from tenant's account to the owner's account
-		let instruction = system_instruction::transfer(&tenant_account.key, &owner_account.key, rent_amount);
+		let agreement = setup(&mut driver);
+		assert_eq!(initialize(&mut driver, &agreement, &init_params(&agreement)), Err(TrustedPropertiesError::ProgramPaused.into()));
+		assert!(driver.account(&agreement.agreement).data.iter().all(|byte| *byte == 0));
 
-		// Invoke the system program to transfer the rent-amount
-		invoke(
-			&instruction,
-			&[
-				system_program_account.clone(),
-				owner_account.clone(),
-				tenant_account.clone(),
+		// Existing agreements keep working
+		pay_rent(&mut driver, &existing, 1).unwrap();
+
+		set_paused(&mut driver, authority, false).unwrap();
+		initialize(&mut driver, &agreement, &init_params(&agreement)).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::DepositPending as u8);
+	}
+
+	#[test]
+	fn test_penalty_shortfall_settled() {
+		let mut driver = ProgramDriver::new();
+		// A full month's rent is more than the deposit held
+		let agreement = active_agreement_with(&mut driver, |params| params.rent_amount = DEPOSIT + RENT);
+
+		terminate_with_proration(&mut driver, &agreement, 30).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), DEPOSIT);
+		assert_eq!(driver.agreement(&agreement.agreement).owed_to_owner, RENT);
+
+		let settle_shortfall = |driver: &mut ProgramDriver| {
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new(agreement.tenant, true),
+					AccountMeta::new(agreement.owner, false),
+					AccountMeta::new_readonly(system_program::id(), false),
+				],
+				vec![8],
+			)
+		};
+
+		settle_shortfall(&mut driver).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), DEPOSIT + RENT);
+		assert_eq!(driver.lamports(&agreement.tenant), 10 * RENT - DEPOSIT - RENT);
+		assert_eq!(driver.agreement(&agreement.agreement).owed_to_owner, 0);
+
+		// Nothing left to settle
+		assert_eq!(settle_shortfall(&mut driver), Err(TrustedPropertiesError::InvalidAgreementStatus.into()));
+	}
+
+	#[test]
+	fn test_crank_auto_pay_when_due() {
+		let mut driver = ProgramDriver::new();
+		let cranker = driver.add_wallet(0);
+		let stranger = driver.add_wallet(0);
+		let agreement = active_agreement(&mut driver);
+		let escrow_lamports = driver.lamports(&agreement.escrow);
+
+		driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new_readonly(cranker, false),
+				AccountMeta::new(agreement.escrow, false),
+				AccountMeta::new_readonly(system_program::id(), false),
 			],
-		)?;
+			vec![27, 2],
+		).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!((rent_data.cranker_pubkey, rent_data.autopay_remaining, rent_data.prepaid_rent), (cranker, 2, 2 * RENT));
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_lamports + 2 * RENT);
 
-		msg!("[TrustedProperties] Transfer completed. Remaining balance of the tenant: {}", tenant_account.lamports());
+		let crank = |driver: &mut ProgramDriver, cranker: Pubkey| {
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new_readonly(cranker, true),
+					AccountMeta::new(agreement.owner, false),
+					AccountMeta::new(agreement.tenant, false),
+					AccountMeta::new(agreement.escrow, false),
+				],
+				vec![9],
+			)
+		};
 
-		// Decrement the number of payment
-		rent_data.remaining_payments -= 1;
-		if rent_data.remaining_payments == 0 {
-			rent_data.status = AgreementStatus::Completed as u8;
-		}
-		rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+		assert_eq!(crank(&mut driver, stranger), Err(ProgramError::InvalidAccountData));
 
-		Ok(())
+		// The first month has started: paid from the prepaid rent
+		crank(&mut driver, cranker).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), RENT);
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 1);
+
+		// The second month has not
+		assert_eq!(crank(&mut driver, cranker), Err(TrustedPropertiesError::InvalidAgreementStatus.into()));
+		assert_eq!(driver.lamports(&agreement.owner), RENT);
+
+		set_now(month_start_ts(2, 2022));
+		crank(&mut driver, cranker).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.status, AgreementStatus::Completed as u8);
+		assert_eq!((rent_data.autopay_remaining, rent_data.prepaid_rent), (0, 0));
+		assert_eq!(driver.lamports(&agreement.owner), 2 * RENT);
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_lamports);
 	}
 
+	#[test]
+	fn test_pay_rent_split_shares() {
+		let mut driver = ProgramDriver::new();
+		let co_owner = driver.add_wallet(0);
+		let stranger = driver.add_wallet(0);
+		let agreement = active_agreement(&mut driver);
 
-	/// Pay the initial security_deposit amount (tenant -> escrow)
-	/// TODO: Revert the security_deposit to the tenant after agreement period
-	/// TODO: 	or, make the last n payments from security_deposit escrow account.
-	/// TODO:	Also, use this for penalty if the tenant terminates the agreement early.
-	/// TODO: Merge with pay_rent function to create a common generic function.
-	fn deposit_security(accounts: &[AccountInfo], program_id: &Pubkey, security_deposit_amount: u64) -> ProgramResult {
+		let mut data = vec![10, 1];
+		data.extend_from_slice(co_owner.as_ref());
+		by_party(&mut driver, &agreement, agreement.owner, data).unwrap();
 
-		let accounts_iter = &mut accounts.iter();
+		let pay_rent_split = |driver: &mut ProgramDriver, splits: &[(Pubkey, u16)]| {
+			let mut data = vec![28, splits.len() as u8];
+			for (payee, share_bps) in splits {
+				data.extend_from_slice(payee.as_ref());
+				data.extend_from_slice(&share_bps.to_le_bytes());
+			}
+			let mut accounts = vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new_readonly(system_program::id(), false),
+			];
+			accounts.extend(splits.iter().map(|(payee, _)| AccountMeta::new(*payee, false)));
+			driver.process(accounts, data)
+		};
 
-		let rent_agreement_account = next_account_info(accounts_iter)?;
-		if rent_agreement_account.owner != program_id {
-			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
-			return Err(ProgramError::IncorrectProgramId);
-		}
+		let invalid: ProgramResult = Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		assert_eq!(pay_rent_split(&mut driver, &[(agreement.owner, 6_000), (co_owner, 3_000)]), invalid);
+		assert_eq!(pay_rent_split(&mut driver, &[(agreement.owner, 6_000), (co_owner, 5_000)]), invalid);
+		// Only the owner & the co-owners can be paid
+		assert_eq!(pay_rent_split(&mut driver, &[(agreement.owner, 6_000), (stranger, 4_000)]), Err(ProgramError::InvalidAccountData));
+		assert_eq!(driver.lamports(&agreement.tenant), 10 * RENT - DEPOSIT);
 
-		let tenant_account = next_account_info(accounts_iter)?;
-		let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
-		let system_program_account = next_account_info(accounts_iter)?;
+		pay_rent_split(&mut driver, &[(agreement.owner, 6_000), (co_owner, 4_000)]).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), RENT * 6 / 10);
+		assert_eq!(driver.lamports(&co_owner), RENT * 4 / 10);
+		assert_eq!(driver.lamports(&agreement.tenant), 9 * RENT - DEPOSIT);
+		assert_eq!(driver.agreement(&agreement.agreement).payments_made, 1);
+	}
 
-		if !tenant_account.is_signer {
-			return Err(ProgramError::MissingRequiredSignature);
-		}
+	#[test]
+	fn test_waive_rent() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement_with(&mut driver, |params| params.duration = 3);
 
-		if tenant_account.lamports() < security_deposit_amount {
-			return Err(ProgramError::InsufficientFunds);
-		}
+		let waive_rent = |driver: &mut ProgramDriver, months: u8| {
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new_readonly(agreement.owner, true),
+					AccountMeta::new_readonly(agreement.escrow, false),
+				],
+				vec![29, months],
+			)
+		};
 
-		// Transfer to self? Do nothing.
-		if tenant_account.key == escrow_account.key {
-			return Ok(());
-		}
+		waive_rent(&mut driver, 1).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!((rent_data.remaining_payments, rent_data.waived_payments, rent_data.status), (2, 1, AgreementStatus::Active as u8));
 
-		// Initialize the Rent Agreement Account with the initial data
-		// Note: the structure of the data state must match the `space` the client used to create the account
-		let rent_agreement_data = RentAgreementAccount::try_from_slice(&rent_agreement_account.data.borrow());
+		assert_eq!(waive_rent(&mut driver, 3), Err(TrustedPropertiesError::InvalidInstructionParameter.into()));
+		assert_eq!(driver.agreement(&agreement.agreement).remaining_payments, 2);
 
-		if rent_agreement_data.is_err() {
-			msg!("[TrustedProperties] Rent agreement account data size incorrect: {}", rent_agreement_account.try_data_len()?);
-			return Err(ProgramError::InvalidAccountData);
-		}
+		// Waiving all the remaining months completes the agreement, without any transfer
+		waive_rent(&mut driver, 2).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!((rent_data.remaining_payments, rent_data.waived_payments, rent_data.status), (0, 3, AgreementStatus::Completed as u8));
+		assert_eq!(driver.lamports(&agreement.owner), 0);
+		assert_eq!(driver.lamports(&agreement.tenant), 10 * RENT - DEPOSIT);
+	}
 
-		let mut rent_data = rent_agreement_data.unwrap();
-		if !rent_data.is_initialized() {
-			msg!("[TrustedProperties] ERROR: Invalid agreement: Rent agreement account not initialized.");
-			return Err(ProgramError::UninitializedAccount);
-		}
+	#[test]
+	fn test_adjust_deposit() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		let escrow_lamports = driver.lamports(&agreement.escrow);
 
-		// Make sure we pay the same account used during the agreement initialization
-		if rent_data.security_escrow_pubkey != *escrow_account.key {
-			msg!("[TrustedProperties] ERROR: Escrow account's public-key does not match the one used during agreement initialization");
-			return Err(ProgramError::InvalidAccountData);
-		}
+		// An increase is paid by the tenant into the escrow
+		adjust_deposit(&mut driver, &agreement, DEPOSIT + RENT).unwrap();
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_lamports + RENT);
+		assert_eq!(driver.lamports(&agreement.tenant), 9 * RENT - DEPOSIT);
+		let escrow = driver.escrow(&agreement.escrow);
+		assert_eq!((escrow.security_deposit, escrow.remaining_deposit), (Lamports(DEPOSIT + RENT), Lamports(DEPOSIT + RENT)));
+		assert_eq!(driver.agreement(&agreement.agreement).security_deposit, DEPOSIT + RENT);
 
-		msg!("[TrustedProperties] Transferring {} lamports from tenant (current balance: {}) to escrow", security_deposit_amount, tenant_account.lamports());
+		// A decrease is refunded to the tenant from the escrow
+		adjust_deposit(&mut driver, &agreement, DEPOSIT - RENT).unwrap();
+		assert_eq!(driver.lamports(&agreement.escrow), escrow_lamports - RENT);
+		assert_eq!(driver.lamports(&agreement.tenant), 11 * RENT - DEPOSIT);
+		let escrow = driver.escrow(&agreement.escrow);
+		assert_eq!((escrow.security_deposit, escrow.remaining_deposit), (Lamports(DEPOSIT - RENT), Lamports(DEPOSIT - RENT)));
+		assert_eq!(driver.agreement(&agreement.agreement).security_deposit, DEPOSIT - RENT);
+	}
 
-		if !rent_data.is_security_deposit_pending() {
-			msg!("[TrustedProperties] ERROR: Security already deposited");
-			return Err(TrustedPropertiesError::SecurityAlreadyDeposited.into());
-		}
+	#[test]
+	fn test_pay_rent_late_fee() {
+		let incorrect_amount: ProgramResult = Err(TrustedPropertiesError::IncorrectPaymentAmount.into());
+		let mut driver = ProgramDriver::new();
+		// Started last month: the first payment is overdue, the second one is due today
+		let agreement = active_agreement_with(&mut driver, |params| {
+			params.start_month = 12;
+			params.start_year = 2021;
+			params.late_fee = LATE_FEE;
+		});
 
-		// TODO: Allow advance payment (transfer amount more than the monthly rent amount)
-		if security_deposit_amount != rent_data.security_deposit {
-			msg!("[TrustedProperties] ERROR: Deposit amount ({}) does not match the agreed amount ({})", security_deposit_amount, rent_data.security_deposit);
-			return Err(TrustedPropertiesError::IncorrectPaymentAmount.into());
-		}
+		assert_eq!(pay_rent(&mut driver, &agreement, 1), incorrect_amount);
+		pay_rent_with(&mut driver, &agreement, RENT + LATE_FEE, 1, None).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), RENT + LATE_FEE);
+		assert_eq!(driver.agreement(&agreement.agreement).late_payment_count, 1);
 
-		// Create instruction to transfer the rent-amount (lamports) from tenant's account to the owner's account
-		let instruction = system_instruction::transfer(&tenant_account.key, &escrow_account.key, security_deposit_amount);
+		// On time: the rent alone
+		assert_eq!(pay_rent_with(&mut driver, &agreement, RENT + LATE_FEE, 2, None), incorrect_amount);
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), 2 * RENT + LATE_FEE);
+		assert_eq!(driver.agreement(&agreement.agreement).late_payment_count, 1);
+	}
 
-		// Invoke the system program to transfer the security deposit amount to the escrow account
-		invoke(
-			&instruction,
-			&[
-				system_program_account.clone(),
-				escrow_account.clone(),
-				tenant_account.clone(),
-			],
-		)?;
+	#[test]
+	fn test_assign_tenancy_requires_both_signers() {
+		let mut driver = ProgramDriver::new();
+		let new_tenant = driver.add_wallet(10 * RENT);
+		let agreement = active_agreement(&mut driver);
 
-		msg!("[TrustedProperties] Security deposit completed. Remaining balance of the tenant: {}", tenant_account.lamports());
+		let assign_tenancy = |driver: &mut ProgramDriver, owner_signs: bool, tenant_signs: bool| {
+			let mut data = vec![32];
+			data.extend_from_slice(new_tenant.as_ref());
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new_readonly(agreement.owner, owner_signs),
+					AccountMeta::new(agreement.tenant, tenant_signs),
+					AccountMeta::new(agreement.escrow, false),
+				],
+				data,
+			)
+		};
 
-		// Deposit payment done. Therefore, mark the agreement account as active.
-		rent_data.status = AgreementStatus::Active as u8;
-		rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+		assert_eq!(assign_tenancy(&mut driver, false, true), Err(ProgramError::MissingRequiredSignature));
+		assert_eq!(assign_tenancy(&mut driver, true, false), Err(ProgramError::MissingRequiredSignature));
+		assert_eq!(driver.agreement(&agreement.agreement).tenant_pubkey, agreement.tenant);
 
-		Ok(())
+		assign_tenancy(&mut driver, true, true).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).tenant_pubkey, new_tenant);
+		let escrow = driver.escrow(&agreement.escrow);
+		assert_eq!((escrow.tenant_pubkey, escrow.remaining_deposit), (new_tenant, Lamports(DEPOSIT)));
+
+		// The new tenant pays the rent from now on
+		let agreement = Agreement { tenant: new_tenant, ..agreement };
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		assert_eq!(driver.lamports(&new_tenant), 9 * RENT);
 	}
 
+	#[test]
+	fn test_pay_rent_without_platform_fee() {
+		let mut driver = ProgramDriver::new();
+		let platform_wallet = driver.add_wallet(0);
+		let agreement = active_agreement_with(&mut driver, |params| params.platform_wallet = platform_wallet);
 
-	/// Terminate the contract early
-	fn terminate_early(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
-		let accounts_iter = &mut accounts.iter();
+		// A zero fee needs no platform wallet, & pays nothing to a platform wallet passed anyway
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		pay_rent_with(&mut driver, &agreement, RENT, 2, Some(platform_wallet)).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), 2 * RENT);
+		assert_eq!(driver.lamports(&platform_wallet), 0);
+	}
 
-		let rent_agreement_account = next_account_info(accounts_iter)?;
-		if rent_agreement_account.owner != program_id {
-			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
-			return Err(ProgramError::IncorrectProgramId);
-		}
+	#[test]
+	fn test_suspend_postpones_the_schedule() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement_with(&mut driver, |params| params.late_fee = LATE_FEE);
 
-		let rent_agreement_data = RentAgreementAccount::try_from_slice(&rent_agreement_account.data.borrow());
-		if rent_agreement_data.is_err() {
-			msg!("[TrustedProperties] ERROR: Incorrect data size ({}) for the Rent agreement account", rent_agreement_account.try_data_len()?);
-			return Err(ProgramError::InvalidAccountData);
-		}
+		by_both_parties(&mut driver, &agreement, vec![36]).unwrap();
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Suspended as u8);
+		assert_eq!(pay_rent(&mut driver, &agreement, 1), Err(TrustedPropertiesError::InvalidAgreementStatus.into()));
 
-		let mut rent_data = rent_agreement_data.unwrap();
-		if !rent_data.is_initialized() {
-			msg!("[TrustedProperties] ERROR: Rent agreement account is not initialized");
-			return Err(ProgramError::UninitializedAccount);
-		}
+		// Resumed 10 days into February: the first payment is due now, not overdue
+		let resumed_ts = month_start_ts(2, 2022) + 10 * 86_400;
+		set_now(resumed_ts);
+		by_both_parties(&mut driver, &agreement, vec![37]).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!(rent_data.status, AgreementStatus::Active as u8);
+		assert_eq!(rent_data.suspended_secs, resumed_ts - month_start_ts(1, 2022));
+		assert_eq!((rent_data.due_ts_for_payment(1), rent_data.due_ts_for_payment(2)), (resumed_ts, month_start_ts(2, 2022) + rent_data.suspended_secs));
 
-		if rent_data.is_completed() {
-			msg!("[TrustedProperties] ERROR: Full rent already paid");
-			return Err(TrustedPropertiesError::RentAlreadyFullyPaid.into());
-		}
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), RENT);
+		assert_eq!(driver.agreement(&agreement.agreement).late_payment_count, 0);
+	}
 
-		if rent_data.is_terminated() {
-			msg!("[TrustedProperties] ERROR: Rent agreement already terminated");
-			return Err(TrustedPropertiesError::RentAgreementTerminated.into());
-		}
+	fn claim_refund(driver: &mut ProgramDriver, agreement: &Agreement) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new_readonly(agreement.agreement, false),
+				AccountMeta::new(agreement.tenant, true),
+				AccountMeta::new(agreement.escrow, false),
+			],
+			vec![38],
+		)
+	}
 
-		rent_data.remaining_payments = 0;
-		rent_data.status = AgreementStatus::Terminated as u8;
-		rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+	#[test]
+	fn test_claim_refund_after_the_windows() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement_with(&mut driver, |params| params.refund_delay_days = 10);
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		pay_rent(&mut driver, &agreement, 2).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		let claim_unlock_ts = rent_data.refund_claim_unlock_ts().unwrap();
+		assert!(claim_unlock_ts > rent_data.refund_unlock_ts().unwrap());
 
-		Ok(())
+		// Past the refund delay, the owner's grace period is still running
+		set_now(rent_data.refund_unlock_ts().unwrap());
+		assert_eq!(claim_refund(&mut driver, &agreement), Err(TrustedPropertiesError::RefundLocked.into()));
+		set_now(claim_unlock_ts - 1);
+		assert_eq!(claim_refund(&mut driver, &agreement), Err(TrustedPropertiesError::RefundLocked.into()));
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT));
+
+		set_now(claim_unlock_ts);
+		claim_refund(&mut driver, &agreement).unwrap();
+		assert_eq!(driver.lamports(&agreement.tenant), 8 * RENT);
+		let escrow = driver.escrow(&agreement.escrow);
+		assert_eq!((escrow.remaining_deposit, escrow.status), (Lamports(0), AgreementStatus::Completed as u8));
 	}
 
+	#[test]
+	fn test_arbiter_resolve_splits_the_deposit() {
+		let mut driver = ProgramDriver::new();
+		let arbiter = driver.add_wallet(0);
+		let agreement = active_agreement_with(&mut driver, |params| params.arbiter_pubkey = arbiter);
+		by_party(&mut driver, &agreement, agreement.owner, vec![17]).unwrap();
+		let tenant_lamports = driver.lamports(&agreement.tenant);
 
-	/// Extend the contract duration.
-	fn extend_contract_duration_request(accounts: &[AccountInfo], program_id: &Pubkey, extension_duration: u8) -> ProgramResult {
+		let arbiter_resolve = |driver: &mut ProgramDriver, arbiter: Pubkey| {
+			let mut data = vec![41];
+			data.extend_from_slice(&7_000u16.to_le_bytes());
+			data.push(true as u8);
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new_readonly(arbiter, true),
+					AccountMeta::new(agreement.tenant, false),
+					AccountMeta::new(agreement.owner, false),
+					AccountMeta::new(agreement.escrow, false),
+				],
+				data,
+			)
+		};
 
-		let accounts_iter = &mut accounts.iter();
+		assert_eq!(arbiter_resolve(&mut driver, agreement.owner), Err(ProgramError::InvalidAccountData));
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(DEPOSIT));
 
-		let rent_agreement_account = next_account_info(accounts_iter)?;
-		if rent_agreement_account.owner != program_id {
-			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
-			return Err(ProgramError::IncorrectProgramId);
-		}
+		arbiter_resolve(&mut driver, arbiter).unwrap();
+		assert_eq!(driver.lamports(&agreement.tenant), tenant_lamports + DEPOSIT * 7 / 10);
+		assert_eq!(driver.lamports(&agreement.owner), DEPOSIT * 3 / 10);
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(0));
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Terminated as u8);
+	}
 
-		let tenant_account = next_account_info(accounts_iter)?;
+	#[test]
+	fn test_initialize_batch() {
+		let mut driver = ProgramDriver::new();
+		let owner = driver.add_wallet(0);
+		let units: Vec<Agreement> = (0..MAX_BATCH_SIZE + 1)
+			.map(|_| Agreement { owner, ..setup(&mut driver) })
+			.collect();
+		let config = driver.config_pda();
 
-		if !tenant_account.is_signer {
-			msg!("[TrustedProperties] Tenant must sign the Duration Extension Request");
-			return Err(ProgramError::MissingRequiredSignature);
-		}
+		let initialize_batch = |driver: &mut ProgramDriver, units: &[Agreement], params: &[InitParams]| {
+			let mut data = vec![44, params.len() as u8];
+			for params in params {
+				data.extend_from_slice(&initialize_data(params)[1..]);
+			}
+			let mut accounts = vec![
+				AccountMeta::new_readonly(owner, true),
+				AccountMeta::new_readonly(config, false),
+			];
+			accounts.extend(units.iter().map(|unit| AccountMeta::new(unit.agreement, false)));
+			driver.process(accounts, data)
+		};
 
-		let rent_agreement_data = RentAgreementAccount::try_from_slice(&rent_agreement_account.data.borrow());
-		if rent_agreement_data.is_err() {
-			msg!("[TrustedProperties] Rent agreement account data size incorrect: {}", rent_agreement_account.try_data_len()?);
-			return Err(ProgramError::InvalidAccountData);
-		}
+		let params: Vec<InitParams> = units.iter().map(init_params).collect();
+		assert_eq!(initialize_batch(&mut driver, &units, &params), Err(TrustedPropertiesError::InvalidInstruction.into()));
 
-		let mut rent_data = rent_agreement_data.unwrap();
-		if !rent_data.is_initialized() {
-			msg!("[TrustedProperties] ERROR: Invalid agreement: Rent agreement account not initialized.");
-			return Err(ProgramError::UninitializedAccount);
+		// Any invalid agreement aborts the whole batch
+		let mut invalid_params: Vec<InitParams> = units[..3].iter().map(init_params).collect();
+		invalid_params[2].rent_amount = 0;
+		assert_eq!(initialize_batch(&mut driver, &units[..3], &invalid_params), Err(TrustedPropertiesError::InvalidInstructionParameter.into()));
+		assert!(driver.account(&units[0].agreement).data.iter().all(|byte| *byte == 0));
+
+		initialize_batch(&mut driver, &units[..3], &params[..3]).unwrap();
+		for unit in &units[..3] {
+			let rent_data = driver.agreement(&unit.agreement);
+			assert_eq!((rent_data.owner_pubkey, rent_data.tenant_pubkey), (owner, unit.tenant));
+			assert_eq!(rent_data.status, AgreementStatus::DepositPending as u8);
 		}
+	}
 
-		if rent_data.status != AgreementStatus::Active as u8 {
-			msg!("[TrustedProperties] ERROR: Agreement must be active to extend the duration");
-			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+	#[test]
+	fn test_admin_force_resolve() {
+		let mut driver = ProgramDriver::new();
+		let authority = driver.add_wallet(0);
+		set_config(&mut driver, ProgramConfig { authority, ..ProgramConfig::default() });
+		let agreement = active_agreement(&mut driver);
+		by_party(&mut driver, &agreement, agreement.tenant, vec![17]).unwrap();
+		let tenant_lamports = driver.lamports(&agreement.tenant);
+		let config = driver.config_pda();
+
+		let force_resolve = |driver: &mut ProgramDriver, authority: Pubkey| {
+			driver.process(
+				vec![
+					AccountMeta::new(agreement.agreement, false),
+					AccountMeta::new_readonly(authority, true),
+					AccountMeta::new_readonly(config, false),
+					AccountMeta::new(agreement.tenant, false),
+					AccountMeta::new(agreement.escrow, false),
+				],
+				vec![45, AgreementStatus::Terminated as u8],
+			)
+		};
+
+		assert_eq!(force_resolve(&mut driver, agreement.owner), Err(ProgramError::InvalidAccountData));
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Disputed as u8);
+
+		// The default disposition: the deposit is refunded to the tenant
+		force_resolve(&mut driver, authority).unwrap();
+		let rent_data = driver.agreement(&agreement.agreement);
+		assert_eq!((rent_data.status, rent_data.remaining_payments), (AgreementStatus::Terminated as u8, 0));
+		assert_eq!(driver.lamports(&agreement.tenant), tenant_lamports + DEPOSIT);
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(0));
+	}
+
+	#[test]
+	fn test_pay_rent_to_self() {
+		for allow_self_rent in [true, false] {
+			let mut driver = ProgramDriver::new();
+			let agreement = setup(&mut driver);
+			// An owner-occupier: the owner is the tenant
+			let agreement = Agreement { owner: agreement.tenant, ..agreement };
+			let params = InitParams { allow_self_rent, ..init_params(&agreement) };
+			initialize(&mut driver, &agreement, &params).unwrap();
+			deposit(&mut driver, &agreement).unwrap();
+			let lamports = driver.lamports(&agreement.tenant);
+
+			if allow_self_rent {
+				pay_rent(&mut driver, &agreement, 1).unwrap();
+				let rent_data = driver.agreement(&agreement.agreement);
+				assert_eq!((rent_data.payments_made, rent_data.remaining_payments), (1, 1));
+			} else {
+				assert_eq!(pay_rent(&mut driver, &agreement, 1), Err(ProgramError::InvalidAccountData));
+				assert_eq!(driver.agreement(&agreement.agreement).payments_made, 0);
+			}
+			assert_eq!(driver.lamports(&agreement.tenant), lamports);
 		}
+	}
 
-		// Update the Agreement Duration Extension request
-		rent_data.duration_extension_request = extension_duration;
-		rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+	fn merge_agreements(driver: &mut ProgramDriver, first: &Agreement, second: &Agreement) -> ProgramResult {
+		driver.process(
+			vec![
+				AccountMeta::new(first.agreement, false),
+				AccountMeta::new(second.agreement, false),
+				AccountMeta::new(first.owner, true),
+				AccountMeta::new_readonly(first.tenant, true),
+				AccountMeta::new(first.escrow, false),
+				AccountMeta::new(second.escrow, false),
+			],
+			vec![48],
+		)
+	}
 
-		Ok(())
+	#[test]
+	fn test_merge_agreements() {
+		let mut driver = ProgramDriver::new();
+		let first = active_agreement(&mut driver);
+		let first_escrow_lamports = driver.lamports(&first.escrow);
+
+		// A second agreement between the same parties, with another rent
+		let other_rent = Agreement { agreement: driver.add_program_account(RentAgreementAccount::LEN), escrow: driver.add_program_account(SecurityEscrowAccount::LEN), ..first };
+		let params = InitParams { rent_amount: 2 * RENT, ..init_params(&other_rent) };
+		initialize(&mut driver, &other_rent, &params).unwrap();
+		deposit(&mut driver, &other_rent).unwrap();
+		assert_eq!(merge_agreements(&mut driver, &first, &other_rent), Err(TrustedPropertiesError::IncorrectPaymentAmount.into()));
+		assert_eq!(driver.agreement(&other_rent.agreement).status, AgreementStatus::Active as u8);
+
+		let second = Agreement { agreement: driver.add_program_account(RentAgreementAccount::LEN), escrow: driver.add_program_account(SecurityEscrowAccount::LEN), ..first };
+		initialize(&mut driver, &second, &init_params(&second)).unwrap();
+		deposit(&mut driver, &second).unwrap();
+		let reclaimed = driver.lamports(&second.agreement);
+
+		merge_agreements(&mut driver, &first, &second).unwrap();
+		let rent_data = driver.agreement(&first.agreement);
+		assert_eq!((rent_data.duration, rent_data.remaining_payments, rent_data.security_deposit), (4, 4, 2 * DEPOSIT));
+		let escrow = driver.escrow(&first.escrow);
+		assert_eq!((escrow.remaining_deposit, escrow.security_deposit), (Lamports(2 * DEPOSIT), Lamports(2 * DEPOSIT)));
+		assert_eq!(driver.lamports(&first.escrow), first_escrow_lamports + DEPOSIT);
+		assert!(driver.escrow(&second.escrow).closed);
+
+		// The second agreement account is closed, its lamports reclaimed by the owner
+		assert_eq!(driver.lamports(&second.agreement), 0);
+		assert!(driver.account(&second.agreement).data.iter().all(|byte| *byte == 0));
+		assert_eq!(driver.lamports(&first.owner), reclaimed);
 	}
 
+	#[test]
+	fn test_pay_rent_window() {
+		let mut driver = ProgramDriver::new();
+		// Started last month: the first payment is overdue, the second one is due today
+		let agreement = active_agreement_with(&mut driver, |params| {
+			params.duration = 3;
+			params.start_month = 12;
+			params.start_year = 2021;
+			params.payment_window_days = 5;
+		});
 
-	/// Confirm the extension of contract duration (by Owner).
-	fn extend_contract_duration_confirm(accounts: &[AccountInfo], program_id: &Pubkey, extension_duration: u8) -> ProgramResult {
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		pay_rent(&mut driver, &agreement, 2).unwrap();
 
-		let accounts_iter = &mut accounts.iter();
+		// The third payment is due next month: the window opens 5 days before
+		assert_eq!(pay_rent(&mut driver, &agreement, 3), Err(TrustedPropertiesError::PaymentTooEarly.into()));
+		set_now(month_start_ts(2, 2022) - 5 * 86_400 - 1);
+		assert_eq!(pay_rent(&mut driver, &agreement, 3), Err(TrustedPropertiesError::PaymentTooEarly.into()));
+		assert_eq!(driver.lamports(&agreement.owner), 2 * RENT);
 
-		let rent_agreement_account = next_account_info(accounts_iter)?;
-		if rent_agreement_account.owner != program_id {
-			msg!("[TrustedProperties] Rent agreement account is not owned by this program");
-			return Err(ProgramError::IncorrectProgramId);
-		}
+		set_now(month_start_ts(2, 2022) - 5 * 86_400);
+		pay_rent(&mut driver, &agreement, 3).unwrap();
+		assert_eq!(driver.lamports(&agreement.owner), 3 * RENT);
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Completed as u8);
+	}
 
-		let owner_account = next_account_info(accounts_iter)?;
+	#[test]
+	fn test_completed_agreement_rejects_mutations() {
+		let finalized: ProgramResult = Err(TrustedPropertiesError::AgreementFinalized.into());
+		let mut driver = ProgramDriver::new();
+		let agreement = completed_agreement(&mut driver);
+		let stored = driver.account(&agreement.agreement).data.clone();
 
-		if !owner_account.is_signer {
-			msg!("[TrustedProperties] Owner must sign the Duration Extension Confirmation");
-			return Err(ProgramError::MissingRequiredSignature);
-		}
+		assert_eq!(by_party(&mut driver, &agreement, agreement.tenant, vec![4, 1]), finalized);
+		assert_eq!(top_up(&mut driver, &agreement, 500), finalized);
+		assert_eq!(by_party(&mut driver, &agreement, agreement.owner, vec![17]), finalized);
+		assert_eq!(terminate_early(&mut driver, &agreement, agreement.owner, true), finalized);
+		assert_eq!(driver.account(&agreement.agreement).data, stored);
 
-		let rent_agreement_data = RentAgreementAccount::try_from_slice(&rent_agreement_account.data.borrow());
-		if rent_agreement_data.is_err() {
-			msg!("[TrustedProperties] Rent agreement account data size incorrect: {}", rent_agreement_account.try_data_len()?);
-			return Err(ProgramError::InvalidAccountData);
-		}
+		// The refund (& reads) still work
+		verify_escrow_solvency(&mut driver, &agreement).unwrap();
+		refund(&mut driver, &agreement).unwrap();
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(0));
+	}
 
-		let mut rent_data = rent_agreement_data.unwrap();
-		if !rent_data.is_initialized() {
-			msg!("[TrustedProperties] ERROR: Invalid agreement: Rent agreement account not initialized.");
-			return Err(ProgramError::UninitializedAccount);
-		}
+	#[test]
+	fn test_finalize_record() {
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
 
-		if rent_data.status != AgreementStatus::Active as u8 {
-			msg!("[TrustedProperties] ERROR: Agreement must be active to extend the duration");
-			return Err(TrustedPropertiesError::InvalidAgreementStatus.into());
+		// Only a finalized agreement can be sealed
+		assert_eq!(
+			by_both_parties(&mut driver, &agreement, vec![49]),
+			Err(TrustedPropertiesError::InvalidAgreementStatus.into())
+		);
+
+		let agreement = completed_agreement(&mut driver);
+
+		// Both parties must sign
+		let owner_only = driver.process(
+			vec![
+				AccountMeta::new(agreement.agreement, false),
+				AccountMeta::new_readonly(agreement.owner, true),
+				AccountMeta::new_readonly(agreement.tenant, false),
+			],
+			vec![49],
+		);
+		assert_eq!(owner_only, Err(ProgramError::MissingRequiredSignature));
+		assert!(!driver.agreement(&agreement.agreement).is_sealed);
+
+		by_both_parties(&mut driver, &agreement, vec![49]).unwrap();
+		assert!(driver.agreement(&agreement.agreement).is_sealed);
+
+		// A sealed agreement can no longer be changed
+		let sealed: ProgramResult = Err(TrustedPropertiesError::AgreementSealed.into());
+		assert_eq!(by_both_parties(&mut driver, &agreement, vec![49]), sealed);
+		assert_eq!(renew(&mut driver, &agreement, 12, RENT), sealed);
+		assert_eq!(driver.agreement(&agreement.agreement).status, AgreementStatus::Completed as u8);
+	}
+
+	#[test]
+	fn test_crank_overdue() {
+		let mut driver = ProgramDriver::new();
+		// Started two months ago: two payments overdue (late fees included), the third one due today
+		let overdue = active_agreement_with(&mut driver, |params| {
+			params.duration = 3;
+			params.start_month = 11;
+			params.start_year = 2021;
+			params.late_fee = LATE_FEE;
+		});
+		// Another agreement of the same tenant, with another owner, starting today: nothing overdue
+		let current = Agreement { tenant: overdue.tenant, ..setup(&mut driver) };
+		initialize(&mut driver, &current, &init_params(&current)).unwrap();
+		deposit(&mut driver, &current).unwrap();
+		let tenant_lamports = driver.lamports(&overdue.tenant);
+
+		let mut accounts = vec![
+			AccountMeta::new(overdue.tenant, true),
+			AccountMeta::new_readonly(system_program::id(), false),
+		];
+		for agreement in [&current, &overdue] {
+			accounts.push(AccountMeta::new(agreement.agreement, false));
+			accounts.push(AccountMeta::new(agreement.owner, false));
+			accounts.push(AccountMeta::new(agreement.escrow, false));
 		}
+		driver.process(accounts, vec![50]).unwrap();
 
-		if rent_data.duration_extension_request != extension_duration {
-			msg!("[TrustedProperties] ERROR: Extension duration ({}) does not match the requested one ({}).", extension_duration, rent_data.duration_extension_request);
-			return Err(TrustedPropertiesError::InvalidInstructionParameter.into());
+		let rent_data = driver.agreement(&overdue.agreement);
+		assert_eq!((rent_data.payments_made, rent_data.remaining_payments), (2, 1));
+		assert_eq!(driver.lamports(&overdue.owner), 2 * (RENT + LATE_FEE));
+		assert_eq!(driver.lamports(&overdue.tenant), tenant_lamports - 2 * (RENT + LATE_FEE));
+
+		let rent_data = driver.agreement(&current.agreement);
+		assert_eq!((rent_data.payments_made, rent_data.remaining_payments), (0, 2));
+		assert_eq!(driver.lamports(&current.owner), 0);
+	}
+
+	#[test]
+	fn test_refund_without_clock() {
+		let clock_unavailable: ProgramResult = Err(TrustedPropertiesError::ClockUnavailable.into());
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		let delayed = active_agreement_with(&mut driver, |params| params.refund_delay_days = 10);
+
+		// Clock-optional mode: the payments complete the agreements, at the due date of their last payment
+		unset_clock();
+		for agreement in [&agreement, &delayed] {
+			pay_rent(&mut driver, agreement, 1).unwrap();
+			pay_rent(&mut driver, agreement, 2).unwrap();
+			let rent_data = driver.agreement(&agreement.agreement);
+			assert_eq!(rent_data.status, AgreementStatus::Completed as u8);
+			assert_eq!(rent_data.completed_ts, month_start_ts(2, 2022));
 		}
 
-		// Update the Agreement Duration Extension
-		rent_data.duration += extension_duration;
-		rent_data.remaining_payments += extension_duration;
-		rent_data.duration_extension_request = 0;
-		rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+		// Without a refund delay, the refund does not need the clock
+		refund(&mut driver, &agreement).unwrap();
+		assert_eq!(driver.escrow(&agreement.escrow).remaining_deposit, Lamports(0));
 
-		Ok(())
+		// The refund delay & the claim grace period do
+		assert_eq!(refund(&mut driver, &delayed), clock_unavailable);
+		assert_eq!(claim_refund(&mut driver, &delayed), clock_unavailable);
+		assert_eq!(driver.escrow(&delayed.escrow).remaining_deposit, Lamports(DEPOSIT));
+
+		set_now(month_start_ts(2, 2022) + 10 * 86_400);
+		refund(&mut driver, &delayed).unwrap();
+		assert_eq!(driver.escrow(&delayed.escrow).remaining_deposit, Lamports(0));
 	}
 
+	#[cfg(feature = "anchor-events")]
+	#[test]
+	fn test_rent_paid_events() {
+		use crate::events::{AnchorEvent, RentPaid};
+
+		/// The `RentPaid` events logged as `Program data: <base64>` lines, decoded back
+		fn rent_paid_events() -> Vec<RentPaid> {
+			take_logs().iter()
+				.filter_map(|line| line.strip_prefix("Program data: "))
+				.map(|data| {
+					let data = base64::decode(data).unwrap();
+					assert_eq!(data[..8], RentPaid::discriminator());
+					RentPaid::try_from_slice(&data[8..]).unwrap()
+				})
+				.collect()
+		}
+
+		let mut driver = ProgramDriver::new();
+		let agreement = active_agreement(&mut driver);
+		take_logs();
+
+		pay_rent(&mut driver, &agreement, 1).unwrap();
+		assert_eq!(rent_paid_events(), vec![RentPaid {
+			agreement_pubkey: agreement.agreement,
+			payment_number: 1,
+			amount: RENT,
+			remaining_payments: 1,
+			event_seq: driver.agreement(&agreement.agreement).event_seq,
+		}]);
+
+		// The other payment paths emit it too: one event per overdue payment paid by the crank, & for an external payment
+		let overdue = active_agreement_with(&mut driver, |params| {
+			params.duration = 3;
+			params.start_month = 11;
+			params.start_year = 2021;
+			params.late_fee = LATE_FEE;
+		});
+		take_logs();
+		driver.process(
+			vec![
+				AccountMeta::new(overdue.tenant, true),
+				AccountMeta::new_readonly(system_program::id(), false),
+				AccountMeta::new(overdue.agreement, false),
+				AccountMeta::new(overdue.owner, false),
+				AccountMeta::new(overdue.escrow, false),
+			],
+			vec![50],
+		).unwrap();
+		let mut data = vec![42];
+		data.extend_from_slice(&[9; 32]);
+		driver.process(
+			vec![
+				AccountMeta::new(overdue.agreement, false),
+				AccountMeta::new_readonly(overdue.owner, true),
+				AccountMeta::new_readonly(overdue.tenant, true),
+				AccountMeta::new_readonly(overdue.escrow, false),
+			],
+			data,
+		).unwrap();
+
+		let event_seq = driver.agreement(&overdue.agreement).event_seq;
+		let paid = |payment_number, amount, remaining_payments, event_seq| RentPaid {
+			agreement_pubkey: overdue.agreement,
+			payment_number,
+			amount,
+			remaining_payments,
+			event_seq,
+		};
+		assert_eq!(rent_paid_events(), vec![
+			paid(1, RENT + LATE_FEE, 1, event_seq - 1),
+			paid(2, RENT + LATE_FEE, 1, event_seq - 1),
+			paid(3, 0, 0, event_seq),
+		]);
+	}
 }
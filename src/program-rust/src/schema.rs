@@ -0,0 +1,63 @@
+/// schema.rs -> account layout documentation (Borsh schema) for clients in other languages
+///
+/// Only compiled with the `schema` feature, to keep the BPF binary lean.
+
+use borsh::schema::{BorshSchema, Definition, Fields};
+
+
+/// Borsh schema of the given type (eg: `RentAgreementAccount`) as JSON.
+/// Lists the container declaration along with the definitions of every type it is made of, in the (de)serialization order.
+pub fn schema_json<T: BorshSchema>() -> String {
+	let container = T::schema_container();
+
+	let mut declarations: Vec<&String> = container.definitions.keys().collect();
+	declarations.sort();
+
+	let definitions: Vec<String> = declarations
+		.iter()
+		.map(|declaration| format!("\"{}\":{}", declaration, definition_json(&container.definitions[*declaration])))
+		.collect();
+
+	format!("{{\"declaration\":\"{}\",\"definitions\":{{{}}}}}", container.declaration, definitions.join(","))
+}
+
+
+fn definition_json(definition: &Definition) -> String {
+	match definition {
+		Definition::Array { length, elements } => format!("{{\"kind\":\"array\",\"length\":{},\"elements\":\"{}\"}}", length, elements),
+		Definition::Sequence { elements } => format!("{{\"kind\":\"sequence\",\"elements\":\"{}\"}}", elements),
+		Definition::Tuple { elements } => format!("{{\"kind\":\"tuple\",\"elements\":[{}]}}", quoted_list(elements)),
+		Definition::Enum { variants } => {
+			let variants: Vec<String> = variants
+				.iter()
+				.map(|(name, declaration)| format!("{{\"name\":\"{}\",\"type\":\"{}\"}}", name, declaration))
+				.collect();
+			format!("{{\"kind\":\"enum\",\"variants\":[{}]}}", variants.join(","))
+		}
+		Definition::Struct { fields } => format!("{{\"kind\":\"struct\",\"fields\":{}}}", fields_json(fields)),
+	}
+}
+
+
+fn fields_json(fields: &Fields) -> String {
+	match fields {
+		Fields::NamedFields(fields) => {
+			let fields: Vec<String> = fields
+				.iter()
+				.map(|(name, declaration)| format!("{{\"name\":\"{}\",\"type\":\"{}\"}}", name, declaration))
+				.collect();
+			format!("[{}]", fields.join(","))
+		}
+		Fields::UnnamedFields(fields) => format!("[{}]", quoted_list(fields)),
+		Fields::Empty => String::from("[]"),
+	}
+}
+
+
+fn quoted_list(items: &[String]) -> String {
+	items
+		.iter()
+		.map(|item| format!("\"{}\"", item))
+		.collect::<Vec<String>>()
+		.join(",")
+}
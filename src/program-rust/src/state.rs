@@ -1,10 +1,51 @@
 /// state.rs -> program objects, (de)serializing state
 
 use solana_program::{
+	msg,
+	native_token::LAMPORTS_PER_SOL,
+	program_error::ProgramError,
 	program_pack::{IsInitialized, Sealed},
 	pubkey::Pubkey,
+	rent::Rent,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "schema")]
+use borsh::BorshSchema;
+use std::{convert::TryFrom, fmt, io};
+
+use crate::error::TrustedPropertiesError;
+
+
+/// Current layout version of the Rent Agreement account
+pub const AGREEMENT_SCHEMA_VERSION: u8 = 2;
+
+/// Seconds in a day
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Days after which a pending duration extension request can no longer be confirmed
+pub const EXTENSION_REQUEST_WINDOW_DAYS: i64 = 14;
+
+/// Seed to derive the Security Deposit Escrow PDA of an agreement (along with the agreement's public-key)
+pub const ESCROW_SEED: &[u8] = b"escrow";
+
+/// Maximum count of co-owners (besides the owner) that can receive a share of the rent
+pub const MAX_CO_OWNERS: usize = 4;
+
+/// Seed to derive the (single) Program Config PDA
+pub const CONFIG_SEED: &[u8] = b"config";
+
+/// Days (after the refund delay) the owner has to refund the security deposit, before the tenant can claim it
+pub const OWNER_RESPONSE_GRACE_DAYS: i64 = 7;
+
+/// Earliest & latest years an agreement term may start or end in
+pub const MIN_AGREEMENT_YEAR: u16 = 1970;
+pub const MAX_AGREEMENT_YEAR: u16 = 9999;
+
+/// Maximum platform fee (in basis points) the marketplace can take from each rent payment (10%)
+pub const MAX_PLATFORM_FEE_BPS: u16 = 1_000;
+
+/// Maximum number of agreements initialized by a single `InitializeBatch`, to stay within the compute budget
+pub const MAX_BATCH_SIZE: usize = 8;
 
 
 /* ==========================================================================
@@ -13,9 +54,14 @@ use borsh::{BorshDeserialize, BorshSerialize};
 
 /// Renting state stored in the Agreement Account
 /// Recording the owner & tenant public keys to ensure that future transactions happen between these parties only.
-#[derive(BorshSerialize, BorshDeserialize, Debug)]				// Traits to (de)serialize & debug
+/// `Default` gives the all-zero (`Uninitialized`) state of a freshly created account.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, PartialEq)]		// Traits to (de)serialize, debug, zero-initialize & compare
+#[cfg_attr(feature = "schema", derive(BorshSchema))]			// Layout documentation for clients (not built for BPF)
 pub struct RentAgreementAccount {
 
+	/// Layout version of the account data (see `AGREEMENT_SCHEMA_VERSION`)
+	pub schema_version: u8,
+
 	/// Agreement status (active, complete, terminated, etc)
 	pub status: u8,
 
@@ -47,7 +93,333 @@ pub struct RentAgreementAccount {
 	pub start_year: u16,
 
 	/// Duration (in months) for contract extension requested by Tenant
-	pub duration_extension_request: u8
+	pub duration_extension_request: u8,
+
+	/// Public-key of the party (owner or tenant) who last raised a dispute
+	pub disputed_by: Pubkey,
+
+	/// Yearly rent escalation (in basis points, eg: 500 = 5%) applied after each full year of payments
+	pub annual_increase_bps: u16,
+
+	/// Count of rent payments made after their due date
+	pub late_payment_count: u8,
+
+	/// Days to hold the security deposit after completion before it can be refunded
+	pub refund_delay_days: u16,
+
+	/// Unix timestamp when the agreement was completed (0 while not completed)
+	pub completed_ts: i64,
+
+	/// Count of monthly payments made (not reset on termination, unlike `remaining_payments`)
+	pub payments_made: u8,
+
+	/// SPL token mint the rent must be paid in (default public-key for native SOL)
+	pub payment_mint: Pubkey,
+
+	/// Unix timestamp when the pending duration extension was requested
+	pub extension_request_ts: i64,
+
+	/// Penalty amount (in Lamports) the security deposit could not cover, still owed by the tenant to the owner
+	pub owed_to_owner: u64,
+
+	/// Hash of the move-in condition report, recorded by the tenant (all-zero until recorded)
+	pub movein_report_hash: [u8; 32],
+
+	/// Hash of the move-out condition report, recorded by the owner (all-zero until recorded)
+	pub moveout_report_hash: [u8; 32],
+
+	/// Count of rent payments the authorized cranker may still trigger (auto-pay)
+	pub autopay_remaining: u8,
+
+	/// Public-key of the keeper authorized by the tenant to trigger the auto-pay rent payments
+	pub cranker_pubkey: Pubkey,
+
+	/// Rent (in Lamports) prepaid by the tenant into the escrow for the auto-pay payments, on top of the security deposit
+	pub prepaid_rent: u64,
+
+	/// Co-owners of the property that can receive a share of the rent (unused slots hold the default public-key)
+	pub co_owners: [Pubkey; MAX_CO_OWNERS],
+
+	/// Count of monthly payments waived by the owner (for audit)
+	pub waived_payments: u8,
+
+	/// Last client-supplied operation nonce applied by `PayRent` (replays of it are ignored)
+	pub last_op_nonce: u64,
+
+	/// Share (in basis points) of the rent charged for a partial first month, eg: 5000 for a mid-month move-in (0 for a full first month)
+	pub first_month_proration_bps: u16,
+
+	/// Fee (in Lamports) charged on top of the rent for a payment made after its due date
+	pub late_fee: u64,
+
+	/// Maximum count of occupants allowed by the agreement (0 for no occupancy clause)
+	pub max_occupants: u8,
+
+	/// Has the owner reported a breach of the maximum occupancy clause?
+	pub occupancy_breach: bool,
+
+	/// Share (in basis points) of each rent payment collected by the marketplace (0 for no platform fee)
+	pub platform_fee_bps: u16,
+
+	/// Marketplace account receiving the platform fee
+	pub platform_wallet: Pubkey,
+
+	/// Unix timestamp when the agreement was suspended by both parties (0 when not suspended)
+	pub suspended_ts: i64,
+
+	/// Total seconds the agreement has spent suspended, postponing the due dates of the remaining payments
+	pub suspended_secs: i64,
+
+	/// Neutral third party who can resolve a dispute by splitting the deposit (none if default/zero)
+	pub arbiter_pubkey: Pubkey,
+
+	/// Count of monthly payments made outside the program (recorded by both parties), for audit
+	pub external_payment_count: u8,
+
+	/// Reference (eg: hash of a bank transfer receipt) of the latest payment made outside the program
+	pub external_payment_ref: [u8; 32],
+
+	/// Can the owner pay themselves the rent (eg: an owner-occupier tracking notional rent)?
+	/// A self-directed payment then counts without any transfer, instead of being rejected.
+	pub allow_self_rent: bool,
+
+	/// Sequence number of the latest state change of the agreement, incremented by every instruction storing it.
+	/// Lets indexers order the events of an agreement independently of the slots.
+	pub event_seq: u64,
+
+	/// Number of days before its due date from which a monthly payment is accepted (0 = any time).
+	/// Overdue payments are always accepted.
+	pub payment_window_days: u8,
+
+	/// Is the (finalized) agreement sealed by both parties as a permanent record? No instruction can change it anymore.
+	pub is_sealed: bool,
+}
+
+
+/// Version 1 layout of the Rent Agreement account: the layout shipped before the version byte, so without `schema_version`.
+/// Kept to migrate older accounts to the current layout. Told apart from the versioned layouts by its size, as its first byte is the status.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct RentAgreementAccountV1 {
+	pub status: u8,
+	pub owner_pubkey: Pubkey,
+	pub tenant_pubkey: Pubkey,
+	pub security_escrow_pubkey: Pubkey,
+	pub security_deposit: u64,
+	pub rent_amount: u64,
+	pub duration: u8,
+	pub remaining_payments: u8,
+	pub start_month: u8,
+	pub start_year: u16,
+	pub duration_extension_request: u8,
+	pub disputed_by: Pubkey,
+	pub annual_increase_bps: u16,
+}
+
+impl RentAgreementAccountV1 {
+	/// Size of the v1 layout
+	pub const LEN: usize = 1			// status
+		+ 32				// owner_pubkey
+		+ 32				// tenant_pubkey
+		+ 32				// security_escrow_pubkey
+		+ 8					// security_deposit
+		+ 8					// rent_amount
+		+ 1					// duration
+		+ 1					// remaining_payments
+		+ 1					// start_month
+		+ 2					// start_year
+		+ 1					// duration_extension_request
+		+ 32				// disputed_by
+		+ 2;				// annual_increase_bps
+
+	/// Every size the v1 layout had before fields were appended to it, oldest first
+	pub const PRIOR_LENS: [usize; 2] = [
+		119,				// before disputed_by
+		151,				// before annual_increase_bps
+	];
+
+	/// Is the account data of the v1 layout (of its final or a prior size)?
+	pub fn is_v1_len(len: usize) -> bool {
+		len == Self::LEN || Self::PRIOR_LENS.contains(&len)
+	}
+}
+
+/// Upgrade a v1 layout to the current version, filling defaults for the newly added fields
+impl From<RentAgreementAccountV1> for RentAgreementAccount {
+	fn from(v1: RentAgreementAccountV1) -> Self {
+		let mut state = RentAgreementAccount {
+			schema_version: AGREEMENT_SCHEMA_VERSION,
+			status: v1.status,
+			owner_pubkey: v1.owner_pubkey,
+			tenant_pubkey: v1.tenant_pubkey,
+			security_escrow_pubkey: v1.security_escrow_pubkey,
+			security_deposit: v1.security_deposit,
+			rent_amount: v1.rent_amount,
+			duration: v1.duration,
+			remaining_payments: v1.remaining_payments,
+			start_month: v1.start_month,
+			start_year: v1.start_year,
+			duration_extension_request: v1.duration_extension_request,
+			disputed_by: v1.disputed_by,
+			annual_increase_bps: v1.annual_increase_bps,
+			late_payment_count: 0,
+			refund_delay_days: 0,
+			completed_ts: 0,
+			payments_made: 0,
+			payment_mint: Pubkey::default(),
+			extension_request_ts: 0,
+			owed_to_owner: 0,
+			movein_report_hash: [0; 32],
+			moveout_report_hash: [0; 32],
+			autopay_remaining: 0,
+			cranker_pubkey: Pubkey::default(),
+			prepaid_rent: 0,
+			co_owners: [Pubkey::default(); MAX_CO_OWNERS],
+			waived_payments: 0,
+			last_op_nonce: 0,
+			first_month_proration_bps: 0,
+			late_fee: 0,
+			max_occupants: 0,
+			occupancy_breach: false,
+			platform_fee_bps: 0,
+			platform_wallet: Pubkey::default(),
+			suspended_ts: 0,
+			suspended_secs: 0,
+			arbiter_pubkey: Pubkey::default(),
+			external_payment_count: 0,
+			external_payment_ref: [0; 32],
+			allow_self_rent: false,
+			event_seq: 0,
+			payment_window_days: 0,
+			is_sealed: false,
+		};
+
+		// Not stored by v1: derived from the payment counters
+		state.payments_made = state.derived_payments_made();
+		state
+	}
+}
+
+
+/* ==========================================================================
+					Return Data: Tenant Reputation
+============================================================================= */
+
+/// Risk summary of the tenant of an agreement, returned by `GetTenantReputation`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct TenantReputation {
+
+	/// Count of rent payments made after their due date
+	pub late_payment_count: u8,
+
+	/// Count of monthly payments made
+	pub payments_made: u8,
+
+	/// Was the agreement terminated early?
+	pub terminated_early: bool,
+
+	/// Was a dispute ever raised on the agreement?
+	pub disputed: bool,
+}
+
+
+/* ==========================================================================
+					Return Data: Financial Ledger
+============================================================================= */
+
+/// Financial statement of an agreement, returned by `GetLedger`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Ledger {
+
+	/// Total rent paid so far (in Lamports)
+	pub total_paid_lamports: u64,
+
+	/// Total rent still due for the remaining payments (in Lamports)
+	pub total_remaining_lamports: u64,
+
+	/// Security deposit currently held in the escrow (in Lamports)
+	pub deposit_held_lamports: u64,
+
+	/// Total amount charged from the security deposit to the owner (in Lamports)
+	pub penalties_applied_lamports: u64,
+
+	/// Penalty shortfall (not covered by the security deposit) still owed to the owner (in Lamports)
+	pub owed_to_owner_lamports: u64,
+}
+
+
+/* ==========================================================================
+					Return Data: Next Payment Due
+============================================================================= */
+
+/// Countdown to the next rent payment of an agreement, returned by `GetNextDue`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct NextDue {
+
+	/// Number (1-based) of the next monthly payment
+	pub payment_number: u8,
+
+	/// Unix timestamp when the next payment is due
+	pub due_ts: i64,
+
+	/// Seconds remaining until the next payment is due (0 once it is due)
+	pub seconds_remaining: i64,
+
+	/// Is the next payment overdue?
+	pub overdue: bool,
+}
+
+
+/* ==========================================================================
+				Return Data: Overdue Crank Summary
+============================================================================= */
+
+/// Outcome of settling the overdue rent of a tenant's agreements, returned by `CrankOverdue`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct CrankOverdueSummary {
+
+	/// Count of agreements whose overdue payments were all paid
+	pub agreements_paid: u8,
+
+	/// Count of agreements skipped (not overdue, not payable by this crank, or not enough funds)
+	pub agreements_skipped: u8,
+
+	/// Count of monthly payments made across the agreements
+	pub payments_made: u16,
+
+	/// Total paid by the tenant to the owners (in Lamports), late fees included
+	pub total_paid_lamports: u64,
+}
+
+
+/* ==========================================================================
+				Account State: Portfolio Summary
+============================================================================= */
+
+/// Aggregate figures across the agreements of an owner, stored in an accumulator account by `AccumulateTvl`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, PartialEq)]	// Traits to (de)serialize, debug, zero-initialize & compare
+#[cfg_attr(feature = "schema", derive(BorshSchema))]			// Layout documentation for clients (not built for BPF)
+pub struct PortfolioSummary {
+
+	/// Property owner account's public-key, common to all the summed agreements
+	pub owner_pubkey: Pubkey,
+
+	/// Count of agreements summed
+	pub agreement_count: u32,
+
+	/// Total still owed by the tenants across the agreements (in Lamports)
+	pub remaining_obligation_lamports: u64,
+
+	/// Total security deposit held in the escrows of the agreements (in Lamports)
+	pub deposits_held_lamports: u64,
+}
+
+impl PortfolioSummary {
+
+	/// Size (in bytes) of the serialized account data, i.e, the `space` to allocate for the accumulator account
+	pub const LEN: usize =
+		32					// owner_pubkey
+		+ 4					// agreement_count
+		+ 8 * 2;			// remaining_obligation_lamports, deposits_held_lamports
 }
 
 
@@ -57,7 +429,8 @@ pub struct RentAgreementAccount {
 
 /// The Security Deposit Escrow Account State
 /// Used to store the security-deposit amount from the tenant
-#[derive(BorshSerialize, BorshDeserialize, Debug)]				// Traits to (de)serialize & debug
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]		// Traits to (de)serialize, debug & compare
+#[cfg_attr(feature = "schema", derive(BorshSchema))]			// Layout documentation for clients (not built for BPF)
 pub struct SecurityEscrowAccount {
 
 	/// Agreement status (active, complete, terminated, etc)
@@ -73,10 +446,99 @@ pub struct SecurityEscrowAccount {
 	pub tenant_pubkey: Pubkey,
 
 	/// Minimum security-deposit amount to be maintained
-	pub security_deposit: u64,
+	pub security_deposit: Lamports,
 
 	/// Currently remaining security deposit amount in the escrow
-	pub remaining_deposit: u64,
+	pub remaining_deposit: Lamports,
+
+	/// Total amount ever deposited into the escrow (initial deposit + top-ups). Never decremented.
+	pub total_deposited: Lamports,
+
+	/// Total amount charged from the deposit to the owner (eg: prorated rent on early termination)
+	pub penalties_applied: Lamports,
+
+	/// Is the escrow closed (eg: after the deposit was migrated to a new escrow account)?
+	pub closed: bool,
+}
+
+
+/* ==========================================================================
+					Account State: Program Config
+============================================================================= */
+
+/// Program-wide settings, stored in the Program Config PDA (derived from `CONFIG_SEED`)
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]		// Traits to (de)serialize, debug & compare
+#[cfg_attr(feature = "schema", derive(BorshSchema))]	// Layout documentation for clients (not built for BPF)
+pub struct ProgramConfig {
+
+	/// Is the initialization of new agreements paused? (existing agreements are not affected)
+	pub paused: bool,
+
+	/// Public-key allowed to update the config (the program's upgrade authority when the config was created)
+	pub authority: Pubkey,
+
+	/// Maximum platform fee (in basis points) a new agreement may take, at most `MAX_PLATFORM_FEE_BPS`
+	pub max_platform_fee_bps: u16,
+
+	/// Maximum duration (in months) of a new agreement
+	pub max_duration: u8,
+
+	/// Minimum security deposit of a new agreement, in months of rent (0 for no minimum)
+	pub min_deposit_months: u8,
+}
+
+/// Layout of the Program Config before the governable parameters were appended to it.
+/// Kept to read (& keep pausing) older config accounts.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ProgramConfigV1 {
+	pub paused: bool,
+	pub authority: Pubkey,
+}
+
+/// Upgrade a v1 config to the current layout, with the default parameters (the ones enforced before they were governable)
+impl From<ProgramConfigV1> for ProgramConfig {
+	fn from(v1: ProgramConfigV1) -> Self {
+		ProgramConfig {
+			paused: v1.paused,
+			authority: v1.authority,
+			..ProgramConfig::default()
+		}
+	}
+}
+
+/// Parameters in effect until the config is created or updated by `SetConfig`
+impl Default for ProgramConfig {
+	fn default() -> Self {
+		ProgramConfig {
+			paused: false,
+			authority: Pubkey::default(),
+			max_platform_fee_bps: MAX_PLATFORM_FEE_BPS,
+			max_duration: u8::MAX,
+			min_deposit_months: 0,
+		}
+	}
+}
+
+impl ProgramConfig {
+
+	/// Size (in bytes) of the serialized config, i.e, the `space` of the Program Config account
+	pub const LEN: usize =
+		1					// paused
+		+ 32				// authority
+		+ 2					// max_platform_fee_bps
+		+ 1					// max_duration
+		+ 1;				// min_deposit_months
+
+	/// Size of a v1 config account (see `ProgramConfigV1`), which has no room for the governable parameters
+	pub const V1_LEN: usize = 1 + 32;
+
+	/// Deserialize the config, upgrading a v1 config account to the current layout with the default parameters
+	pub fn try_from_slice_versioned(data: &[u8]) -> io::Result<Self> {
+		if data.len() == Self::V1_LEN {
+			return ProgramConfigV1::try_from_slice(data).map(Self::from);
+		}
+		Self::try_from_slice(data)
+	}
 }
 
 
@@ -84,6 +546,52 @@ impl Sealed for RentAgreementAccount {}
 impl Sealed for SecurityEscrowAccount {}
 
 
+impl SecurityEscrowAccount {
+
+	/// Size (in bytes) of the serialized account data, i.e, the `space` to allocate for the Security Deposit Escrow account
+	pub const LEN: usize =
+		1					// status
+		+ 32 * 3			// agreement_pubkey, owner_pubkey, tenant_pubkey
+		+ 8 * 4				// security_deposit, remaining_deposit, total_deposited, penalties_applied
+		+ 1;				// closed
+
+	/// Take `amount` (in Lamports) out of the remaining deposit (eg: a refund, a penalty or a migration).
+	/// Fails with `InsufficientFunds` when the escrow does not hold that much, instead of underflowing.
+	/// Only updates the tracked balance: moving the lamports is up to the caller.
+	pub fn debit(&mut self, amount: u64) -> Result<(), ProgramError> {
+		self.remaining_deposit = self.remaining_deposit
+			.checked_sub(Lamports(amount))
+			.ok_or_else(|| {
+				msg!("[TrustedProperties] ERROR: Escrow debit of {} exceeds the remaining deposit: {}", amount, self.remaining_deposit);
+				ProgramError::InsufficientFunds
+			})?;
+		msg!("[TrustedProperties] Escrow debited: {}. Remaining deposit: {}", amount, self.remaining_deposit);
+		Ok(())
+	}
+
+	/// Add `amount` (in Lamports) to the remaining deposit (eg: a deposit or a top-up)
+	pub fn credit(&mut self, amount: u64) -> Result<(), ProgramError> {
+		self.remaining_deposit = self.remaining_deposit
+			.checked_add(Lamports(amount))
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		msg!("[TrustedProperties] Escrow credited: {}. Remaining deposit: {}", amount, self.remaining_deposit);
+		Ok(())
+	}
+
+	/// Is the escrow fresh, i.e, not holding (nor having ever held) the deposit of any agreement?
+	pub fn is_fresh(&self) -> bool {
+		self.status == AgreementStatus::Uninitialized as u8 && self.remaining_deposit == Lamports(0)
+	}
+
+	/// Amount (in Lamports) of the escrow free to be refunded or claimed: the remaining security deposit.
+	/// The escrow accrues no interest & reserves nothing out of the deposit; the rent prepaid for auto-pay is
+	/// tracked by the agreement (`prepaid_rent`) and is not included, nor is the escrow's rent-exempt minimum.
+	pub fn available_balance(&self) -> u64 {
+		self.remaining_deposit.into()
+	}
+}
+
+
 /// Is the `Agreement Account` initialized?
 impl IsInitialized for RentAgreementAccount {
 	fn is_initialized(&self) -> bool {
@@ -93,11 +601,197 @@ impl IsInitialized for RentAgreementAccount {
 
 impl RentAgreementAccount {
 
+	/// Size (in bytes) of the serialized account data, i.e, the `space` to allocate for the Rent Agreement account.
+	/// Must account for every field of the current layout.
+	pub const LEN: usize =
+		1					// schema_version
+		+ 1					// status
+		+ 32 * 3			// owner_pubkey, tenant_pubkey, security_escrow_pubkey
+		+ 8 * 2				// security_deposit, rent_amount
+		+ 1 + 1 + 1			// duration, remaining_payments, start_month
+		+ 2					// start_year
+		+ 1					// duration_extension_request
+		+ 32				// disputed_by
+		+ 2					// annual_increase_bps
+		+ 1					// late_payment_count
+		+ 2					// refund_delay_days
+		+ 8					// completed_ts
+		+ 1					// payments_made
+		+ 32				// payment_mint
+		+ 8					// extension_request_ts
+		+ 8					// owed_to_owner
+		+ 32 * 2			// movein_report_hash, moveout_report_hash
+		+ 1					// autopay_remaining
+		+ 32				// cranker_pubkey
+		+ 8					// prepaid_rent
+		+ 32 * MAX_CO_OWNERS	// co_owners
+		+ 1					// waived_payments
+		+ 8					// last_op_nonce
+		+ 2					// first_month_proration_bps
+		+ 8					// late_fee
+		+ 1					// max_occupants
+		+ 1					// occupancy_breach
+		+ 2					// platform_fee_bps
+		+ 32				// platform_wallet
+		+ 8					// suspended_ts
+		+ 8					// suspended_secs
+		+ 32				// arbiter_pubkey
+		+ 1					// external_payment_count
+		+ 32				// external_payment_ref
+		+ 1					// allow_self_rent
+		+ 8					// event_seq
+		+ 1					// payment_window_days
+		+ 1;				// is_sealed
+
+	/// Every size the current layout version had before fields were appended to it, oldest first.
+	/// Accounts of these sizes decode with the appended fields at their (zeroed) defaults.
+	pub const PRIOR_LENS: [usize; 22] = [
+		154,				// before late_payment_count
+		155,				// before refund_delay_days, completed_ts
+		165,				// before payments_made
+		166,				// before payment_mint
+		198,				// before extension_request_ts
+		206,				// before owed_to_owner
+		214,				// before movein_report_hash, moveout_report_hash
+		278,				// before autopay_remaining, cranker_pubkey, prepaid_rent
+		319,				// before co_owners
+		447,				// before waived_payments
+		448,				// before last_op_nonce
+		456,				// before first_month_proration_bps
+		458,				// before late_fee
+		466,				// before max_occupants, occupancy_breach
+		468,				// before platform_fee_bps, platform_wallet
+		502,				// before suspended_ts, suspended_secs
+		518,				// before arbiter_pubkey
+		550,				// before external_payment_count, external_payment_ref
+		583,				// before allow_self_rent
+		584,				// before event_seq
+		592,				// before payment_window_days
+		593,				// before is_sealed
+	];
+
+	/// Size of the current layout version once `payments_made` was appended: smaller accounts only tell it by `remaining_payments`
+	const PAYMENTS_MADE_LEN: usize = 166;
+
+	/// New agreement between the owner & tenant, waiting for the tenant's security deposit.
+	/// All payments are pending; optional terms (escalation, refund delay, token mint) are left at their defaults.
+	/// The single place initializing the coupled payment counters (`remaining_payments == duration`, no `payments_made`),
+	/// which `validate_invariants` enforces until the deposit is made.
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		owner_pubkey: Pubkey,
+		tenant_pubkey: Pubkey,
+		security_escrow_pubkey: Pubkey,
+		security_deposit: u64,
+		rent_amount: u64,
+		duration: u8,
+		start_month: u8,
+		start_year: u16,
+	) -> Self {
+		RentAgreementAccount {
+			schema_version: AGREEMENT_SCHEMA_VERSION,
+			status: AgreementStatus::DepositPending as u8,
+			owner_pubkey,
+			tenant_pubkey,
+			security_escrow_pubkey,
+			security_deposit,
+			rent_amount,
+			duration,
+			remaining_payments: duration,
+			start_month,
+			start_year,
+			..Default::default()
+		}
+	}
+
+	/// Deserialize the account data, branching on the layout version byte (or the size of the unversioned v1 layout).
+	/// Older layouts are migrated to the current one (note: the account must be resized before a migrated state can be written back).
+	/// An account of a known prior size (see `PRIOR_LENS`) is decoded with the fields appended since at their defaults.
+	pub fn try_from_slice_versioned(data: &[u8]) -> io::Result<Self> {
+		if RentAgreementAccountV1::is_v1_len(data.len()) {
+			let mut padded = data.to_vec();
+			padded.resize(RentAgreementAccountV1::LEN, 0);
+			return RentAgreementAccountV1::try_from_slice(&padded).map(RentAgreementAccount::from);
+		}
+
+		match data.first() {
+			Some(&AGREEMENT_SCHEMA_VERSION) if Self::PRIOR_LENS.contains(&data.len()) => {
+				// Every appended field defaults to zero, so zero-padding the missing suffix yields the defaults
+				let mut padded = data.to_vec();
+				padded.resize(Self::LEN, 0);
+				let mut state = RentAgreementAccount::try_from_slice(&padded)?;
+
+				// Except the payments made, counted by the older accounts as the payments no longer remaining
+				if data.len() < Self::PAYMENTS_MADE_LEN {
+					state.payments_made = state.derived_payments_made();
+				}
+				Ok(state)
+			}
+			_ => RentAgreementAccount::try_from_slice(data),
+		}
+	}
+
+	/// Payments made, as derived from the payment counters of a layout without `payments_made`.
+	/// A terminated agreement cleared its remaining payments, so no longer tells: counted as none.
+	fn derived_payments_made(&self) -> u8 {
+		if self.status == AgreementStatus::Terminated as u8 {
+			return 0;
+		}
+		self.duration.saturating_sub(self.remaining_payments)
+	}
+
+	/// Check the relationships between the fields that must always hold, to catch a bug before an inconsistent state is persisted.
+	pub fn validate_invariants(&self) -> Result<(), ProgramError> {
+		let violation = match AgreementStatus::try_from(self.status) {
+			Err(_) => Some("unknown status"),
+			Ok(AgreementStatus::Completed) if self.remaining_payments != 0 => Some("completed with payments remaining"),
+			Ok(AgreementStatus::Terminated) if self.remaining_payments != 0 => Some("terminated with payments remaining"),
+			Ok(AgreementStatus::DepositPending) if self.remaining_payments != self.duration || self.payments_made != 0 => Some("payments recorded before the deposit"),
+			_ if self.remaining_payments > self.duration => Some("more payments remaining than the duration"),
+			_ if !(1..=12).contains(&self.start_month) => Some("start month out of range"),
+			_ => None,
+		};
+
+		if let Some(violation) = violation {
+			msg!("[TrustedProperties] ERROR: Corrupt agreement state: {} (status: {}, remaining payments: {}, duration: {}, start month: {})", violation, self.status, self.remaining_payments, self.duration, self.start_month);
+			return Err(TrustedPropertiesError::CorruptState.into());
+		}
+
+		Ok(())
+	}
+
+	/// Advance the event sequence number of the agreement, logging it for indexers.
+	/// Called once by every instruction storing the agreement state.
+	pub fn next_event_seq(&mut self) -> Result<u64, ProgramError> {
+		self.event_seq = self.event_seq
+			.checked_add(1)
+			.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		msg!("[TrustedProperties] Agreement event #{}", self.event_seq);
+		Ok(self.event_seq)
+	}
+
+	/// Is the public-key the owner or one of the co-owners of the property (i.e, can it receive a share of the rent)?
+	pub fn is_owner_payee(&self, payee: &Pubkey) -> bool {
+		*payee == self.owner_pubkey || (*payee != Pubkey::default() && self.co_owners.contains(payee))
+	}
+
 	/// Is initial security_deposit pending by the tenant?
 	pub fn is_security_deposit_pending(&self) -> bool {
 		self.status == AgreementStatus::DepositPending as u8
 	}
 
+	/// Is the security deposit refund waiting for the move-out condition report?
+	/// Only agreements with a recorded move-in report require both reports before the refund.
+	pub fn is_condition_report_pending(&self) -> bool {
+		self.movein_report_hash != [0; 32] && self.moveout_report_hash == [0; 32]
+	}
+
+	/// Can money move right now (rent payments, escrow top-ups, duration extensions)?
+	/// Only an active agreement is: not while the deposit is pending, after completion or termination, or during a dispute.
+	pub fn is_financially_active(&self) -> bool {
+		self.status == AgreementStatus::Active as u8
+	}
+
 	/// Is the rent-agreement complete (i.e, all payments done for the agreed duration)?
 	pub fn is_completed(&self) -> bool {
 		self.status == AgreementStatus::Completed as u8
@@ -108,12 +802,281 @@ impl RentAgreementAccount {
 		self.status == AgreementStatus::Terminated as u8
 	}
 
-	// Get rent-agreement status as String
-	// pub fn get_status(&self) -> String {
-	// 	match self.status {
-	// 		AgreementStatus::Uninitialized => String::from("Hello, world!")
-	// 	}
-	// }
+	/// Is the rent-agreement finalized (i.e, completed or terminated)?
+	pub fn is_finalized(&self) -> bool {
+		self.is_completed() || self.is_terminated()
+	}
+
+	/// Is the rent-agreement on hold due to a dispute between the owner & tenant?
+	pub fn is_disputed(&self) -> bool {
+		self.status == AgreementStatus::Disputed as u8
+	}
+
+	/// Is the rent-agreement suspended by mutual agreement of the owner & tenant (eg: extended travel)?
+	pub fn is_suspended(&self) -> bool {
+		self.status == AgreementStatus::Suspended as u8
+	}
+
+	/// Has the agreement designated an arbiter to resolve its disputes?
+	pub fn has_arbiter(&self) -> bool {
+		self.arbiter_pubkey != Pubkey::default()
+	}
+
+	/// Role of the public-key in the agreement: "owner", "tenant", "co-owner" or "arbiter", if it is a party to it.
+	/// Unset (default) keys never match.
+	pub fn party_role(&self, key: &Pubkey) -> Option<&'static str> {
+		if *key == Pubkey::default() {
+			None
+		} else if *key == self.owner_pubkey {
+			Some("owner")
+		} else if *key == self.tenant_pubkey {
+			Some("tenant")
+		} else if self.co_owners.contains(key) {
+			Some("co-owner")
+		} else if *key == self.arbiter_pubkey {
+			Some("arbiter")
+		} else {
+			None
+		}
+	}
+
+	/// Is the public-key a party to the agreement (owner, tenant, co-owner or arbiter)?
+	pub fn is_party(&self, key: &Pubkey) -> bool {
+		self.party_role(key).is_some()
+	}
+
+	/// Monthly rent amount (in Lamports)
+	pub fn rent_amount_lamports(&self) -> u64 {
+		self.rent_amount
+	}
+
+	/// Monthly rent amount (in SOL)
+	pub fn rent_amount_sol(&self) -> f64 {
+		self.rent_amount as f64 / LAMPORTS_PER_SOL as f64
+	}
+
+	/// Minimum security deposit (in Lamports)
+	pub fn security_deposit_lamports(&self) -> u64 {
+		self.security_deposit
+	}
+
+	/// Minimum security deposit (in SOL)
+	pub fn security_deposit_sol(&self) -> f64 {
+		self.security_deposit as f64 / LAMPORTS_PER_SOL as f64
+	}
+
+	/// Is the rent paid in SPL tokens (rather than native SOL)?
+	pub fn is_token_denominated(&self) -> bool {
+		self.payment_mint != Pubkey::default()
+	}
+
+	/// Rent amount (in Lamports) due for the n-th monthly payment (1-based).
+	/// The `annual_increase_bps` escalation is compounded after each full year of payments,
+	/// i.e., payments 1-12 are charged the base rent, payments 13-24 the once-escalated rent, and so on.
+	/// The first payment is prorated by `first_month_proration_bps`, when set.
+	/// Fails with `ArithmeticOverflow` when the escalated rent does not fit in a `u64`.
+	pub fn rent_for_payment(&self, n: u8) -> Result<u64, ProgramError> {
+		if n == 1 && self.first_month_proration_bps > 0 {
+			return Ok(split_bps(self.rent_amount, self.first_month_proration_bps).0);
+		}
+
+		let years = n.saturating_sub(1) / 12;
+		let mut rent = self.rent_amount as u128;
+		for _ in 0..years {
+			rent = rent
+				.checked_mul(10_000 + self.annual_increase_bps as u128)
+				.ok_or(TrustedPropertiesError::ArithmeticOverflow)?
+				/ 10_000;
+		}
+		u64::try_from(rent).map_err(|_| TrustedPropertiesError::ArithmeticOverflow.into())
+	}
+
+	/// Amount (in Lamports) to pay for the n-th monthly payment (1-based) at the given unix timestamp: its rent, plus the late fee when overdue
+	pub fn amount_due_for_payment(&self, n: u8, now: i64) -> Result<u64, ProgramError> {
+		let rent = self.rent_for_payment(n)?;
+		if self.is_payment_late(n, now) {
+			rent.checked_add(self.late_fee).ok_or_else(|| TrustedPropertiesError::ArithmeticOverflow.into())
+		} else {
+			Ok(rent)
+		}
+	}
+
+	/// Total rent (in Lamports) for the payments `from..=to` (1-based), including any escalation
+	pub fn total_rent_for_payments(&self, from: u8, to: u8) -> Result<u64, ProgramError> {
+		let mut total: u64 = 0;
+		for n in from..=to {
+			total = total
+				.checked_add(self.rent_for_payment(n)?)
+				.ok_or(TrustedPropertiesError::ArithmeticOverflow)?;
+		}
+		Ok(total)
+	}
+
+	/// Total rent (in Lamports) paid so far
+	pub fn total_rent_paid(&self) -> Result<u64, ProgramError> {
+		self.total_rent_for_payments(1, self.payments_made)
+	}
+
+	/// Total rent (in Lamports) still due for the remaining payments
+	pub fn total_rent_remaining(&self) -> Result<u64, ProgramError> {
+		self.total_rent_for_payments(self.payments_made.saturating_add(1), self.payments_made.saturating_add(self.remaining_payments))
+	}
+
+	/// Total (in Lamports) the tenant still owes the owner: the rent of the remaining payments & any penalty shortfall
+	pub fn remaining_obligation_lamports(&self) -> Result<u64, ProgramError> {
+		let rent_remaining = if self.is_terminated() { 0 } else { self.total_rent_remaining()? };
+		rent_remaining.checked_add(self.owed_to_owner).ok_or_else(|| TrustedPropertiesError::ArithmeticOverflow.into())
+	}
+
+	/// Zero-based index (0-11) of the start month, the base of the date helpers' month arithmetic.
+	/// A start month out of 1-12 (eg: 0 in legacy, unvalidated accounts) is clamped into range with a warning, instead of underflowing.
+	fn start_month_index(&self) -> u32 {
+		let start_month = self.start_month.clamp(1, 12);
+		if start_month != self.start_month {
+			msg!("[TrustedProperties] WARNING: Start month {} out of range, read as {}", self.start_month, start_month);
+		}
+		start_month as u32 - 1
+	}
+
+	/// Unix timestamp of the due date of the n-th monthly payment (1-based).
+	/// Rent is prepaid, i.e., each payment is due by the start (1st, 00:00 UTC) of the month it pays for,
+	/// postponed by the time the agreement has spent suspended.
+	pub fn due_ts_for_payment(&self, n: u8) -> i64 {
+		let month_index = self.start_month_index() + n.saturating_sub(1) as u32;
+		let month = (month_index % 12 + 1) as u8;
+		let year = self.start_year as u32 + month_index / 12;
+		month_start_ts(month, year).saturating_add(self.suspended_secs)
+	}
+
+	/// Is the n-th monthly payment (1-based) due (i.e, its month has started) at the given unix timestamp?
+	pub fn is_payment_due(&self, n: u8, now: i64) -> bool {
+		now >= self.due_ts_for_payment(n)
+	}
+
+	/// Payment schedule of the whole duration: the month (1-12) & year each monthly payment pays for, and whether it is paid.
+	/// Years are capped at `u16::MAX`, keeping the serialized schedule of the longest (255 months) agreement within the 1024 bytes of return data.
+	pub fn schedule(&self) -> Vec<(u8, u16, bool)> {
+		let start_month_index = self.start_month_index();
+		(1..=self.duration)
+			.map(|n| {
+				let month_index = start_month_index + (n - 1) as u32;
+				let month = (month_index % 12 + 1) as u8;
+				let year = (self.start_year as u32 + month_index / 12).min(u16::MAX as u32) as u16;
+				(month, year, n <= self.payments_made)
+			})
+			.collect()
+	}
+
+	/// Month (1-12) & year of the last month of the agreement, i.e, the lease ends at the end of this month
+	pub fn end_month_year(&self) -> (u8, u32) {
+		let month_index = self.start_month_index() + (self.duration as u32).saturating_sub(1);
+		((month_index % 12 + 1) as u8, self.start_year as u32 + month_index / 12)
+	}
+
+	/// Would the n-th monthly payment (1-based) be late if made at the given unix timestamp?
+	pub fn is_payment_late(&self, n: u8, now: i64) -> bool {
+		now > self.due_ts_for_payment(n)
+	}
+
+	/// Is the n-th monthly payment (1-based) made too early at the given unix timestamp, i.e., before the payment window opens?
+	/// Always false without a payment window.
+	pub fn is_payment_too_early(&self, n: u8, now: i64) -> bool {
+		self.payment_window_days > 0
+			&& now < self.due_ts_for_payment(n).saturating_sub(self.payment_window_days as i64 * SECONDS_PER_DAY)
+	}
+
+	/// Unix timestamp from which the security deposit can be refunded (after completion + refund delay)
+	pub fn refund_unlock_ts(&self) -> Result<i64, ProgramError> {
+		self.completed_ts
+			.checked_add(self.refund_delay_days as i64 * SECONDS_PER_DAY)
+			.ok_or_else(|| TrustedPropertiesError::ArithmeticOverflow.into())
+	}
+
+	/// Unix timestamp after which the tenant can claim the security deposit refund (the owner did not refund it in time)
+	pub fn refund_claim_unlock_ts(&self) -> Result<i64, ProgramError> {
+		self.refund_unlock_ts()?
+			.checked_add(OWNER_RESPONSE_GRACE_DAYS * SECONDS_PER_DAY)
+			.ok_or_else(|| TrustedPropertiesError::ArithmeticOverflow.into())
+	}
+
+	/// Has the pending duration extension request expired at the given unix timestamp?
+	pub fn is_extension_request_stale(&self, now: i64) -> bool {
+		now > self.extension_request_ts + EXTENSION_REQUEST_WINDOW_DAYS * SECONDS_PER_DAY
+	}
+
+	/// Names of the state-changing instructions that would be accepted for the current agreement status.
+	/// Handy for clients to enable/disable actions without guessing the state machine.
+	pub fn allowed_next_actions(&self) -> Vec<&'static str> {
+		match AgreementStatus::try_from(self.status) {
+			Ok(AgreementStatus::Uninitialized) => vec!["InitializeRentContract"],
+			Ok(AgreementStatus::DepositPending) => vec!["DepositSecurity", "TerminateEarly"],
+			Ok(AgreementStatus::Active) => {
+				let mut actions = if self.is_token_denominated() {
+					vec!["PayRentToken"]
+				} else if self.autopay_remaining > 0 {
+					vec!["CrankAutoPay"]
+				} else {
+					vec!["PayRent", "AuthorizeAutoPay"]
+				};
+				actions.push("TerminateEarly");
+				actions.push("TerminateWithProration");
+				actions.push("RequestContractDurationExtension");
+				if self.duration_extension_request > 0 {
+					actions.push("ConfirmContractDurationExtension");
+				}
+				actions.push("TopUpEscrow");
+				actions.push("MigrateEscrow");
+				actions.push("RaiseDispute");
+				actions.push("SuspendAgreement");
+				actions
+			}
+			Ok(AgreementStatus::Completed) if self.is_sealed => vec!["RefundSecurityDeposit", "ClaimRefund"],
+			Ok(AgreementStatus::Completed) => vec!["RefundSecurityDeposit", "ClaimRefund", "RenewAgreement", "FinalizeRecord"],
+			Ok(AgreementStatus::Terminated) if self.is_sealed => vec![],
+			Ok(AgreementStatus::Terminated) if self.owed_to_owner > 0 => vec!["SettleShortfall", "FinalizeRecord"],
+			Ok(AgreementStatus::Terminated) => vec!["FinalizeRecord"],
+			Ok(AgreementStatus::Disputed) if self.has_arbiter() => vec!["ResolveDispute", "ArbiterResolve"],
+			Ok(AgreementStatus::Disputed) => vec!["ResolveDispute"],
+			Ok(AgreementStatus::Suspended) => vec!["ResumeAgreement"],
+			Err(_) => vec![],
+		}
+	}
+
+	/// Get rent-agreement status as a human-readable label (eg: "Active"), or "Unknown" for an invalid status byte
+	pub fn status_label(&self) -> &'static str {
+		AgreementStatus::try_from(self.status).map_or("Unknown", |status| status.label())
+	}
+
+	/// Human-readable, multi-line summary of the agreement (eg: for CLI tooling).
+	/// Public-keys are truncated & amounts are shown in SOL.
+	pub fn summary(&self) -> String {
+		format!(
+			"Status: {}\n\
+			Owner: {}\n\
+			Tenant: {}\n\
+			Rent: {} SOL / month\n\
+			Security deposit: {} SOL\n\
+			Duration: {} months, starting {:02}/{}\n\
+			Payments: {} made, {} remaining",
+			self.status_label(),
+			truncate_pubkey(&self.owner_pubkey),
+			truncate_pubkey(&self.tenant_pubkey),
+			self.rent_amount_sol(),
+			self.security_deposit_sol(),
+			self.duration,
+			self.start_month,
+			self.start_year,
+			self.payments_made,
+			self.remaining_payments,
+		)
+	}
+}
+
+
+/// Shorten a public-key for display, keeping its first & last 4 characters (eg: "7xKX…AsU9")
+fn truncate_pubkey(key: &Pubkey) -> String {
+	let key = key.to_string();
+	format!("{}…{}", &key[..4], &key[key.len() - 4..])
 }
 
 
@@ -124,4 +1087,615 @@ pub enum AgreementStatus {
 	Active,
 	Completed,
 	Terminated,
+	Disputed,
+	Suspended,
+}
+
+impl AgreementStatus {
+	/// Human-readable label of the status
+	pub fn label(&self) -> &'static str {
+		match self {
+			AgreementStatus::Uninitialized => "Uninitialized",
+			AgreementStatus::DepositPending => "Deposit Pending",
+			AgreementStatus::Active => "Active",
+			AgreementStatus::Completed => "Completed",
+			AgreementStatus::Terminated => "Terminated",
+			AgreementStatus::Disputed => "Disputed",
+			AgreementStatus::Suspended => "Suspended",
+		}
+	}
+}
+
+impl TryFrom<u8> for AgreementStatus {
+	type Error = ProgramError;
+
+	fn try_from(status: u8) -> Result<Self, Self::Error> {
+		match status {
+			0 => Ok(AgreementStatus::Uninitialized),
+			1 => Ok(AgreementStatus::DepositPending),
+			2 => Ok(AgreementStatus::Active),
+			3 => Ok(AgreementStatus::Completed),
+			4 => Ok(AgreementStatus::Terminated),
+			5 => Ok(AgreementStatus::Disputed),
+			6 => Ok(AgreementStatus::Suspended),
+			_ => Err(ProgramError::InvalidAccountData),
+		}
+	}
+}
+
+
+/// An amount of Lamports, to tell monetary values apart from counts & basis points.
+/// Serialized exactly like a plain `u64`, so a field can be wrapped without changing the account layout.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+pub struct Lamports(pub u64);
+
+impl Lamports {
+	pub fn checked_add(self, other: Lamports) -> Option<Lamports> {
+		self.0.checked_add(other.0).map(Lamports)
+	}
+
+	pub fn checked_sub(self, other: Lamports) -> Option<Lamports> {
+		self.0.checked_sub(other.0).map(Lamports)
+	}
+
+	pub fn saturating_add(self, other: Lamports) -> Lamports {
+		Lamports(self.0.saturating_add(other.0))
+	}
+
+	pub fn saturating_sub(self, other: Lamports) -> Lamports {
+		Lamports(self.0.saturating_sub(other.0))
+	}
+}
+
+impl From<u64> for Lamports {
+	fn from(amount: u64) -> Self {
+		Lamports(amount)
+	}
+}
+
+impl From<Lamports> for u64 {
+	fn from(amount: Lamports) -> Self {
+		amount.0
+	}
+}
+
+impl fmt::Display for Lamports {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+
+/// Net lamport flows of terminating an agreement with proration, computed by `compute_settlement`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Settlement {
+	/// Rent (in Lamports) for the days occupied in the current month (the full month after an occupancy breach)
+	pub prorated_rent: u64,
+
+	/// Charged from the security deposit to the owner (at most the remaining deposit)
+	pub deposit_to_owner: u64,
+
+	/// Remaining security deposit refunded to the tenant
+	pub deposit_to_tenant: u64,
+
+	/// Unused rent prepaid for auto-pay, refunded to the tenant
+	pub prepaid_to_tenant: u64,
+
+	/// Prorated rent the deposit does not cover, still owed by the tenant to the owner
+	pub shortfall: u64,
+}
+
+impl Settlement {
+	/// Total (in Lamports) moved from the escrow to the tenant
+	pub fn to_tenant(&self) -> u64 {
+		self.deposit_to_tenant.saturating_add(self.prepaid_to_tenant)
+	}
+}
+
+/// Compute the settlement of a termination after `days_occupied` days of the current month:
+/// the prorated rent is charged from the remaining deposit, the rest of the deposit & any prepaid rent go back to the tenant,
+/// and whatever the deposit does not cover is owed to the owner.
+/// Pure money math, with no account access, so that every termination applies the same flows.
+pub fn compute_settlement(agreement: &RentAgreementAccount, escrow: &SecurityEscrowAccount, days_occupied: u8) -> Result<Settlement, ProgramError> {
+	let month_rent = agreement.rent_for_payment(agreement.payments_made.saturating_add(1))?;
+	let prorated_rent = if agreement.occupancy_breach {
+		month_rent
+	} else {
+		u64::try_from(month_rent as u128 * days_occupied as u128 / 30)
+			.map_err(|_| TrustedPropertiesError::ArithmeticOverflow)?
+	};
+
+	let remaining_deposit = u64::from(escrow.remaining_deposit);
+	let deposit_to_owner = prorated_rent.min(remaining_deposit);
+
+	Ok(Settlement {
+		prorated_rent,
+		deposit_to_owner,
+		deposit_to_tenant: remaining_deposit - deposit_to_owner,
+		prepaid_to_tenant: agreement.prepaid_rent,
+		shortfall: prorated_rent - deposit_to_owner,
+	})
+}
+
+
+/// Split `total` Lamports into the share of `bps` basis points (capped at 10000) & the remainder.
+/// The share is rounded down, so the rounding remainder always goes to the remainder: both parts sum exactly to `total`.
+pub fn split_bps(total: u64, bps: u16) -> (u64, u64) {
+	let share = (total as u128 * bps.min(10_000) as u128 / 10_000) as u64;
+	(share, total - share)
+}
+
+/// Minimum balance (in Lamports) for a Rent Agreement account of `RentAgreementAccount::LEN` bytes to be rent exempt.
+/// Clients should fund new agreement accounts with it, to pass the program's rent exemption checks.
+pub fn agreement_rent_exempt_minimum(rent: &Rent) -> u64 {
+	rent.minimum_balance(RentAgreementAccount::LEN)
+}
+
+/// Minimum balance (in Lamports) for a Security Deposit Escrow account of `SecurityEscrowAccount::LEN` bytes to be rent exempt
+pub fn escrow_rent_exempt_minimum(rent: &Rent) -> u64 {
+	rent.minimum_balance(SecurityEscrowAccount::LEN)
+}
+
+
+/// Unix timestamp of the start (1st, 00:00 UTC) of the given month (1-12) & year.
+/// Uses the days-from-civil algorithm for the proleptic Gregorian calendar.
+pub fn month_start_ts(month: u8, year: u32) -> i64 {
+	let m = month as i64;
+	let y = if m <= 2 { year as i64 - 1 } else { year as i64 };
+	let era = if y >= 0 { y } else { y - 399 } / 400;
+	let yoe = y - era * 400;
+	let doy = (153 * ((m + 9) % 12) + 2) / 5;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	let days = era * 146_097 + doe - 719_468;
+	days * SECONDS_PER_DAY
+}
+
+
+/* ==========================================================================
+					Test Helpers: Account Snapshots
+============================================================================= */
+
+/// Test-only helpers to set up program accounts in memory (snapshot) & read their state back (restore)
+#[cfg(test)]
+pub(crate) mod test_utils {
+	use super::*;
+	use solana_program::account_info::AccountInfo;
+
+	/// In-memory account owning the key, owner, lamports & data an `AccountInfo` borrows
+	pub(crate) struct TestAccount {
+		pub key: Pubkey,
+		pub owner: Pubkey,
+		pub lamports: u64,
+		pub data: Vec<u8>,
+		pub is_signer: bool,
+		pub is_writable: bool,
+	}
+
+	impl TestAccount {
+
+		/// Writable, non-signer account with a unique key
+		pub fn new(owner: Pubkey, lamports: u64, data: Vec<u8>) -> Self {
+			TestAccount {
+				key: Pubkey::new_unique(),
+				owner,
+				lamports,
+				data,
+				is_signer: false,
+				is_writable: true,
+			}
+		}
+
+		/// Borrow the account as an `AccountInfo`, as passed to the processor
+		pub fn account_info(&mut self) -> AccountInfo<'_> {
+			AccountInfo::new(&self.key, self.is_signer, self.is_writable, &mut self.lamports, &mut self.data, &self.owner, false, 0)
+		}
+	}
+
+	/// Snapshot: a Rent Agreement account holding `state`, serialized into exactly `RentAgreementAccount::LEN` bytes
+	pub(crate) fn agreement_account(state: &RentAgreementAccount, owner: Pubkey, lamports: u64) -> TestAccount {
+		let data = state.try_to_vec().unwrap();
+		assert_eq!(data.len(), RentAgreementAccount::LEN);
+		TestAccount::new(owner, lamports, data)
+	}
+
+	/// Restore: the Rent Agreement state stored in an account
+	pub(crate) fn read_agreement(account: &AccountInfo) -> RentAgreementAccount {
+		RentAgreementAccount::try_from_slice_versioned(&account.data.borrow()).unwrap()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::test_utils::*;
+
+	/// An active agreement with a non-default value in every field
+	fn sample_agreement() -> RentAgreementAccount {
+		RentAgreementAccount {
+			schema_version: AGREEMENT_SCHEMA_VERSION,
+			status: AgreementStatus::Active as u8,
+			owner_pubkey: Pubkey::new_unique(),
+			tenant_pubkey: Pubkey::new_unique(),
+			security_escrow_pubkey: Pubkey::new_unique(),
+			security_deposit: 2 * LAMPORTS_PER_SOL,
+			rent_amount: LAMPORTS_PER_SOL,
+			duration: 12,
+			remaining_payments: 9,
+			start_month: 3,
+			start_year: 2022,
+			duration_extension_request: 2,
+			disputed_by: Pubkey::new_unique(),
+			annual_increase_bps: 500,
+			late_payment_count: 1,
+			refund_delay_days: 30,
+			completed_ts: 1_650_000_000,
+			payments_made: 3,
+			payment_mint: Pubkey::new_unique(),
+			extension_request_ts: 1_660_000_000,
+			owed_to_owner: 1_000,
+			movein_report_hash: [1; 32],
+			moveout_report_hash: [2; 32],
+			autopay_remaining: 4,
+			cranker_pubkey: Pubkey::new_unique(),
+			prepaid_rent: 4 * LAMPORTS_PER_SOL,
+			co_owners: [Pubkey::new_unique(); MAX_CO_OWNERS],
+			waived_payments: 1,
+			last_op_nonce: 42,
+			first_month_proration_bps: 5_000,
+			late_fee: 10_000,
+			max_occupants: 3,
+			occupancy_breach: true,
+			platform_fee_bps: 100,
+			platform_wallet: Pubkey::new_unique(),
+			suspended_ts: 1_670_000_000,
+			suspended_secs: 86_400,
+			arbiter_pubkey: Pubkey::new_unique(),
+			external_payment_count: 2,
+			external_payment_ref: [3; 32],
+			allow_self_rent: true,
+			event_seq: 7,
+			payment_window_days: 5,
+			is_sealed: true,
+		}
+	}
+
+	#[test]
+	fn test_agreement_snapshot_round_trip() {
+		let state = sample_agreement();
+		let program_id = Pubkey::new_unique();
+
+		let mut account = agreement_account(&state, program_id, 1_000_000);
+		let account_info = account.account_info();
+		assert_eq!(account_info.owner, &program_id);
+		assert_eq!(account_info.lamports(), 1_000_000);
+		assert_eq!(account_info.data_len(), RentAgreementAccount::LEN);
+
+		// Every field is preserved
+		let restored = read_agreement(&account_info);
+		assert_eq!(format!("{:?}", restored), format!("{:?}", state));
+		assert_eq!(restored.try_to_vec().unwrap(), state.try_to_vec().unwrap());
+	}
+
+	/// An agreement with every numeric field at its maximum, every flag set & every byte array filled
+	fn maxed_agreement() -> RentAgreementAccount {
+		RentAgreementAccount {
+			schema_version: u8::MAX,
+			status: u8::MAX,
+			owner_pubkey: Pubkey::new_unique(),
+			tenant_pubkey: Pubkey::new_unique(),
+			security_escrow_pubkey: Pubkey::new_unique(),
+			security_deposit: u64::MAX,
+			rent_amount: u64::MAX,
+			duration: u8::MAX,
+			remaining_payments: u8::MAX,
+			start_month: u8::MAX,
+			start_year: u16::MAX,
+			duration_extension_request: u8::MAX,
+			disputed_by: Pubkey::new_unique(),
+			annual_increase_bps: u16::MAX,
+			late_payment_count: u8::MAX,
+			refund_delay_days: u16::MAX,
+			completed_ts: i64::MAX,
+			payments_made: u8::MAX,
+			payment_mint: Pubkey::new_unique(),
+			extension_request_ts: i64::MAX,
+			owed_to_owner: u64::MAX,
+			movein_report_hash: [u8::MAX; 32],
+			moveout_report_hash: [u8::MAX; 32],
+			autopay_remaining: u8::MAX,
+			cranker_pubkey: Pubkey::new_unique(),
+			prepaid_rent: u64::MAX,
+			co_owners: [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()],
+			waived_payments: u8::MAX,
+			last_op_nonce: u64::MAX,
+			first_month_proration_bps: u16::MAX,
+			late_fee: u64::MAX,
+			max_occupants: u8::MAX,
+			occupancy_breach: true,
+			platform_fee_bps: u16::MAX,
+			platform_wallet: Pubkey::new_unique(),
+			suspended_ts: i64::MAX,
+			suspended_secs: i64::MAX,
+			arbiter_pubkey: Pubkey::new_unique(),
+			external_payment_count: u8::MAX,
+			external_payment_ref: [u8::MAX; 32],
+			allow_self_rent: true,
+			event_seq: u64::MAX,
+			payment_window_days: u8::MAX,
+			is_sealed: true,
+		}
+	}
+
+	#[test]
+	fn test_agreement_round_trip_boundary_values() {
+		for state in [RentAgreementAccount::default(), sample_agreement(), maxed_agreement()] {
+			let data = state.try_to_vec().unwrap();
+			assert_eq!(data.len(), RentAgreementAccount::LEN);
+			assert_eq!(RentAgreementAccount::try_from_slice(&data).unwrap(), state);
+		}
+
+		// The minimum signed timestamps too
+		let state = RentAgreementAccount { completed_ts: i64::MIN, suspended_ts: i64::MIN, suspended_secs: i64::MIN, ..maxed_agreement() };
+		assert_eq!(RentAgreementAccount::try_from_slice(&state.try_to_vec().unwrap()).unwrap(), state);
+	}
+
+	#[test]
+	fn test_agreement_prior_lens_decode_with_defaults() {
+		let state = sample_agreement();
+		let data = state.try_to_vec().unwrap();
+
+		for prior_len in RentAgreementAccount::PRIOR_LENS.iter() {
+
+			// An account of a prior size holds the same prefix, with the fields appended since at their defaults
+			// (the payments made derived, before they were stored)
+			let mut expected_data = data[..*prior_len].to_vec();
+			expected_data.resize(RentAgreementAccount::LEN, 0);
+			let mut expected = RentAgreementAccount::try_from_slice(&expected_data).unwrap();
+			if *prior_len < 166 {
+				expected.payments_made = state.payments_made;
+			}
+
+			let decoded = RentAgreementAccount::try_from_slice_versioned(&data[..*prior_len]).unwrap();
+			assert_eq!(decoded, expected, "prior size {}", prior_len);
+			assert_eq!(decoded.owner_pubkey, state.owner_pubkey);
+			assert_eq!(decoded.rent_amount, state.rent_amount);
+		}
+
+		// The latest appended field only
+		let decoded = RentAgreementAccount::try_from_slice_versioned(&data[..RentAgreementAccount::LEN - 1]).unwrap();
+		assert_eq!(decoded, RentAgreementAccount { is_sealed: false, ..RentAgreementAccount::try_from_slice(&data).unwrap() });
+
+		// Any other size is rejected
+		assert!(RentAgreementAccount::try_from_slice_versioned(&data[..500]).is_err());
+	}
+
+	#[test]
+	fn test_agreement_prior_lens_before_payments_made() {
+		let active = RentAgreementAccount { payments_made: 0, ..sample_agreement() };
+		let data = active.try_to_vec().unwrap();
+
+		// The payments made are derived from the remaining payments (12 - 9) ...
+		let decoded = RentAgreementAccount::try_from_slice_versioned(&data[..165]).unwrap();
+		assert_eq!(decoded.payments_made, 3);
+		assert_eq!(decoded.remaining_payments, 9);
+
+		// ... but not for a terminated agreement (its remaining payments cleared)
+		let terminated = RentAgreementAccount { status: AgreementStatus::Terminated as u8, remaining_payments: 0, ..active };
+		let decoded = RentAgreementAccount::try_from_slice_versioned(&terminated.try_to_vec().unwrap()[..154]).unwrap();
+		assert_eq!(decoded.payments_made, 0);
+		assert!(decoded.validate_invariants().is_ok());
+
+		// Once stored by the account, they are never derived
+		let decoded = RentAgreementAccount::try_from_slice_versioned(&data[..166]).unwrap();
+		assert_eq!(decoded.payments_made, 0);
+	}
+
+	/// Account data of the v1 layout, packed field by field as the v1 program stored it (without the version byte)
+	fn v1_account_data(status: u8, remaining_payments: u8, parties: &[Pubkey; 4]) -> Vec<u8> {
+		let mut data = vec![status];
+		data.extend_from_slice(parties[0].as_ref());		// owner_pubkey
+		data.extend_from_slice(parties[1].as_ref());		// tenant_pubkey
+		data.extend_from_slice(parties[2].as_ref());		// security_escrow_pubkey
+		data.extend_from_slice(&2_000u64.to_le_bytes());	// security_deposit
+		data.extend_from_slice(&1_000u64.to_le_bytes());	// rent_amount
+		data.push(12);										// duration
+		data.push(remaining_payments);
+		data.push(6);										// start_month
+		data.extend_from_slice(&2021u16.to_le_bytes());		// start_year
+		data.push(1);										// duration_extension_request
+		data.extend_from_slice(parties[3].as_ref());		// disputed_by
+		data.extend_from_slice(&500u16.to_le_bytes());		// annual_increase_bps
+		assert_eq!(data.len(), RentAgreementAccountV1::LEN);
+		data
+	}
+
+	#[test]
+	fn test_agreement_v1_migration() {
+		let parties = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+
+		// An active agreement: its first byte (the status) is the same as the current version byte
+		let data = v1_account_data(AgreementStatus::Active as u8, 8, &parties);
+		assert_eq!(data[0], AGREEMENT_SCHEMA_VERSION);
+
+		let migrated = RentAgreementAccount::try_from_slice_versioned(&data).unwrap();
+		let expected = RentAgreementAccount {
+			schema_version: AGREEMENT_SCHEMA_VERSION,
+			status: AgreementStatus::Active as u8,
+			owner_pubkey: parties[0],
+			tenant_pubkey: parties[1],
+			security_escrow_pubkey: parties[2],
+			security_deposit: 2_000,
+			rent_amount: 1_000,
+			duration: 12,
+			remaining_payments: 8,
+			start_month: 6,
+			start_year: 2021,
+			duration_extension_request: 1,
+			disputed_by: parties[3],
+			annual_increase_bps: 500,
+			payments_made: 4,
+			..Default::default()
+		};
+		assert_eq!(migrated, expected);
+		assert!(migrated.validate_invariants().is_ok());
+
+		// A terminated agreement cleared its remaining payments: its payments made are unknown
+		let data = v1_account_data(AgreementStatus::Terminated as u8, 0, &parties);
+		let migrated = RentAgreementAccount::try_from_slice_versioned(&data).unwrap();
+		assert_eq!(migrated.status, AgreementStatus::Terminated as u8);
+		assert_eq!(migrated.payments_made, 0);
+		assert!(migrated.validate_invariants().is_ok());
+
+		// The prior sizes of v1, with the fields appended since at their defaults
+		let data = v1_account_data(AgreementStatus::Active as u8, 8, &parties);
+		let migrated = RentAgreementAccount::try_from_slice_versioned(&data[..151]).unwrap();
+		assert_eq!(migrated, RentAgreementAccount { annual_increase_bps: 0, ..expected });
+		let migrated = RentAgreementAccount::try_from_slice_versioned(&data[..119]).unwrap();
+		assert_eq!(migrated, RentAgreementAccount { annual_increase_bps: 0, disputed_by: Pubkey::default(), ..expected });
+	}
+
+	#[test]
+	fn test_escrow_round_trip_boundary_values() {
+		let escrows = vec![
+			SecurityEscrowAccount {
+				status: 0,
+				agreement_pubkey: Pubkey::default(),
+				owner_pubkey: Pubkey::default(),
+				tenant_pubkey: Pubkey::default(),
+				security_deposit: Lamports(0),
+				remaining_deposit: Lamports(0),
+				total_deposited: Lamports(0),
+				penalties_applied: Lamports(0),
+				closed: false,
+			},
+			SecurityEscrowAccount {
+				status: u8::MAX,
+				agreement_pubkey: Pubkey::new_unique(),
+				owner_pubkey: Pubkey::new_unique(),
+				tenant_pubkey: Pubkey::new_unique(),
+				security_deposit: Lamports(u64::MAX),
+				remaining_deposit: Lamports(u64::MAX),
+				total_deposited: Lamports(u64::MAX),
+				penalties_applied: Lamports(u64::MAX),
+				closed: true,
+			},
+		];
+
+		for escrow in escrows {
+			let data = escrow.try_to_vec().unwrap();
+			assert_eq!(data.len(), SecurityEscrowAccount::LEN);
+			assert_eq!(SecurityEscrowAccount::try_from_slice(&data).unwrap(), escrow);
+		}
+	}
+
+	#[test]
+	fn test_config_round_trip_boundary_values() {
+		let configs = vec![
+			ProgramConfig { paused: false, authority: Pubkey::default(), max_platform_fee_bps: 0, max_duration: 0, min_deposit_months: 0 },
+			ProgramConfig { paused: true, authority: Pubkey::new_unique(), max_platform_fee_bps: u16::MAX, max_duration: u8::MAX, min_deposit_months: u8::MAX },
+		];
+
+		for config in configs {
+			let data = config.try_to_vec().unwrap();
+			assert_eq!(data.len(), ProgramConfig::LEN);
+			assert_eq!(ProgramConfig::try_from_slice_versioned(&data).unwrap(), config);
+		}
+	}
+
+	#[test]
+	fn test_config_v1_migration() {
+		let v1 = ProgramConfigV1 { paused: true, authority: Pubkey::new_unique() };
+		let data = v1.try_to_vec().unwrap();
+		assert_eq!(data.len(), ProgramConfig::V1_LEN);
+
+		let migrated = ProgramConfig::try_from_slice_versioned(&data).unwrap();
+		assert_eq!(migrated, ProgramConfig { paused: true, authority: v1.authority, ..ProgramConfig::default() });
+	}
+
+	#[test]
+	fn test_portfolio_round_trip_boundary_values() {
+		let summaries = vec![
+			PortfolioSummary::default(),
+			PortfolioSummary {
+				owner_pubkey: Pubkey::new_unique(),
+				agreement_count: u32::MAX,
+				remaining_obligation_lamports: u64::MAX,
+				deposits_held_lamports: u64::MAX,
+			},
+		];
+
+		for summary in summaries {
+			let data = summary.try_to_vec().unwrap();
+			assert_eq!(data.len(), PortfolioSummary::LEN);
+			assert_eq!(PortfolioSummary::try_from_slice(&data).unwrap(), summary);
+		}
+	}
+
+	#[test]
+	fn test_validate_invariants_rejects_inconsistent_state() {
+		let corrupt: Result<(), ProgramError> = Err(TrustedPropertiesError::CorruptState.into());
+		assert_eq!(sample_agreement().validate_invariants(), Ok(()));
+
+		let mut state = sample_agreement();
+		state.remaining_payments = state.duration + 1;
+		assert_eq!(state.validate_invariants(), corrupt);
+
+		let mut state = sample_agreement();
+		state.status = AgreementStatus::Completed as u8;
+		assert_eq!(state.validate_invariants(), corrupt);
+
+		let mut state = sample_agreement();
+		state.status = AgreementStatus::DepositPending as u8;
+		assert_eq!(state.validate_invariants(), corrupt);
+
+		let mut state = sample_agreement();
+		state.start_month = 13;
+		assert_eq!(state.validate_invariants(), corrupt);
+
+		let mut state = sample_agreement();
+		state.status = u8::MAX;
+		assert_eq!(state.validate_invariants(), corrupt);
+	}
+
+	#[test]
+	fn test_refund_unlock_ts_overflow() {
+		let overflow: Result<i64, ProgramError> = Err(TrustedPropertiesError::ArithmeticOverflow.into());
+
+		let state = RentAgreementAccount { completed_ts: 1_000, refund_delay_days: 2, ..sample_agreement() };
+		assert_eq!(state.refund_unlock_ts(), Ok(1_000 + 2 * SECONDS_PER_DAY));
+		assert_eq!(state.refund_claim_unlock_ts(), Ok(1_000 + (2 + OWNER_RESPONSE_GRACE_DAYS) * SECONDS_PER_DAY));
+
+		let state = RentAgreementAccount { completed_ts: i64::MAX - SECONDS_PER_DAY, refund_delay_days: 2, ..sample_agreement() };
+		assert_eq!(state.refund_unlock_ts(), overflow);
+		assert_eq!(state.refund_claim_unlock_ts(), overflow);
+
+		// Only the grace period overflows
+		let state = RentAgreementAccount { completed_ts: i64::MAX - SECONDS_PER_DAY, refund_delay_days: 0, ..sample_agreement() };
+		assert_eq!(state.refund_unlock_ts(), Ok(i64::MAX - SECONDS_PER_DAY));
+		assert_eq!(state.refund_claim_unlock_ts(), overflow);
+	}
+
+	#[test]
+	fn test_escrow_available_balance() {
+		let escrow = |security_deposit: u64, remaining_deposit: u64, total_deposited: u64, penalties_applied: u64| SecurityEscrowAccount {
+			status: AgreementStatus::Active as u8,
+			agreement_pubkey: Pubkey::new_unique(),
+			owner_pubkey: Pubkey::new_unique(),
+			tenant_pubkey: Pubkey::new_unique(),
+			security_deposit: Lamports(security_deposit),
+			remaining_deposit: Lamports(remaining_deposit),
+			total_deposited: Lamports(total_deposited),
+			penalties_applied: Lamports(penalties_applied),
+			closed: false,
+		};
+
+		// The remaining deposit, whatever was deposited in total or charged as penalties
+		assert_eq!(escrow(1_000, 1_000, 1_000, 0).available_balance(), 1_000);
+		assert_eq!(escrow(1_000, 600, 1_000, 400).available_balance(), 600);
+		assert_eq!(escrow(1_000, 1_000, 1_400, 400).available_balance(), 1_000);
+		assert_eq!(escrow(1_000, 0, 1_000, 1_000).available_balance(), 0);
+		assert_eq!(escrow(500, 1_500, 1_500, 0).available_balance(), 1_500);
+	}
 }